@@ -0,0 +1,26 @@
+use bevy::color::Color;
+
+use crate::dash::DashPattern;
+
+/// Part of the width of a line, like a lane of a road.
+/// Bands go across the line from the left side to the right side, and share its corners and caps.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Band {
+    /// Part of the width, relative to the other bands
+    pub fraction: f32,
+    /// Color of the band. The color of the line is used if there is none
+    pub color: Option<Color>,
+    /// Draw the band as dashes, with the color of the line in the gaps
+    pub dash_pattern: Option<DashPattern>,
+}
+
+impl Band {
+    pub fn new(fraction: f32, color: Color) -> Self {
+        Band { fraction, color: Some(color), dash_pattern: None }
+    }
+
+    /// Dashes of the color, over the color of the line
+    pub fn dashed(fraction: f32, color: Color, pattern: DashPattern) -> Self {
+        Band { fraction, color: Some(color), dash_pattern: Some(pattern) }
+    }
+}
@@ -0,0 +1,37 @@
+use bevy::color::Color;
+
+/// Band of another color along both sides of a line, like the casing of a road.
+/// It follows the corners and caps of the line.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Border {
+    /// Width of the band on each side, in world units
+    pub width: f32,
+    pub color: Color,
+    pub placement: BorderPlacement,
+}
+
+/// Where the border is placed relative to the sides of the line
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BorderPlacement {
+    /// Inside the width of the line, which keeps its size
+    Inside,
+    /// Outside the width of the line, which gets wider
+    Outside,
+    /// Centered on the sides of the line
+    Both,
+}
+
+impl Border {
+    pub fn new(width: f32, color: Color, placement: BorderPlacement) -> Self {
+        Border { width, color, placement }
+    }
+
+    /// How much the line gets wider, with the border on both sides
+    pub(crate) fn added_width(&self) -> f32 {
+        match self.placement {
+            BorderPlacement::Inside => 0.,
+            BorderPlacement::Outside => 2. * self.width,
+            BorderPlacement::Both => self.width,
+        }
+    }
+}
@@ -10,7 +10,7 @@ pub struct FlexLine2dBundle {
     pub visibility: Visibility,
     pub inherited_visibility: InheritedVisibility,
     pub view_visibility: ViewVisibility,
-    pub material: Handle<ColorMaterial>,
+    pub material: Handle<FlexLineMaterial>,
     pub mesh: Mesh2dHandle,
 }
 
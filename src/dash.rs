@@ -0,0 +1,124 @@
+use crate::error::FlexLineError;
+
+/// Most dashes cut from a line, so tiny patterns cannot make huge meshes
+const MAX_DASHES: f32 = 10000.;
+
+/// A pattern of dashes and gaps, measured in world units along the line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DashPattern {
+    /// Alternating lengths of dashes and gaps, starting with a dash.
+    /// An odd number of lengths is repeated twice, like SVG's `stroke-dasharray`.
+    pub lengths: Vec<f32>,
+    /// How far into the pattern the line starts.
+    pub offset: f32,
+}
+
+impl DashPattern {
+    pub fn new(lengths: Vec<f32>, offset: f32) -> Self {
+        DashPattern { lengths, offset }
+    }
+
+    /// Dashes of equal length, separated by gaps.
+    pub fn dashed(dash: f32, gap: f32) -> Self {
+        Self::new(vec![dash, gap], 0.)
+    }
+
+    /// Dashes of zero length, so only the caps are drawn.
+    /// Combine with round caps to get dots.
+    pub fn dotted(spacing: f32) -> Self {
+        Self::new(vec![0., spacing], 0.)
+    }
+
+    fn lengths(&self) -> Vec<f32> {
+        if self.lengths.len() % 2 == 1 {
+            self.lengths.repeat(2)
+        } else {
+            self.lengths.clone()
+        }
+    }
+
+    /// Check that the lengths are finite and not negative, add up to a positive length, and that the offset is finite.
+    /// Dashes of zero length are allowed, as they are drawn as their caps.
+    pub(crate) fn validate(&self) -> Result<(), FlexLineError> {
+        if let Some(length) = self.lengths.iter().find(|length| !length.is_finite() || **length < 0.) {
+            return Err(FlexLineError::InvalidDashLength(*length));
+        }
+        if self.lengths.iter().sum::<f32>() <= 0. {
+            return Err(FlexLineError::EmptyDashPattern);
+        }
+        if !self.offset.is_finite() {
+            return Err(FlexLineError::InvalidDashOffset(self.offset));
+        }
+        Ok(())
+    }
+
+    /// The ranges between `from` and `to` that are covered by dashes.
+    /// Distances are measured from where the pattern is offset from.
+    /// Patterns that are invalid, or would make more than [`MAX_DASHES`] dashes, cover the whole range.
+    pub(crate) fn ranges(&self, from: f32, to: f32) -> Vec<(f32, f32)> {
+        let lengths = self.lengths();
+        let period: f32 = lengths.iter().sum();
+        if self.validate().is_err() || (to - from) / period > MAX_DASHES {
+            return vec![(from, to)];
+        }
+
+        let mut ranges = Vec::new();
+        let mut cursor = from - (from + self.offset).rem_euclid(period);
+        'outer: loop {
+            let start = cursor;
+            for (i, length) in lengths.iter().enumerate() {
+                if cursor > to {
+                    break 'outer;
+                }
+
+                let end = cursor + length;
+                let (start, stop) = (cursor.max(from), end.min(to));
+                // Dashes of zero length are kept, as they still get caps
+                if i % 2 == 0 && (stop > start || (*length == 0. && cursor >= from)) {
+                    ranges.push((start, stop));
+                }
+                cursor = end;
+            }
+            // Far from the origin, a period can be too short to move the cursor
+            if cursor <= start {
+                break;
+            }
+        }
+        ranges
+    }
+}
+
+#[test]
+fn test_dash_ranges() {
+    let pattern = DashPattern::dashed(2., 1.);
+    assert_eq!(pattern.ranges(0., 7.), vec![(0., 2.), (3., 5.), (6., 7.)]);
+}
+
+#[test]
+fn test_dash_ranges_offset() {
+    let pattern = DashPattern::new(vec![2., 1.], 1.);
+    assert_eq!(pattern.ranges(0., 5.), vec![(0., 1.), (2., 4.)]);
+}
+
+#[test]
+fn test_dash_ranges_dotted() {
+    let pattern = DashPattern::dotted(2.);
+    assert_eq!(pattern.ranges(0., 5.), vec![(0., 0.), (2., 2.), (4., 4.)]);
+}
+
+#[test]
+fn test_dash_ranges_odd() {
+    let pattern = DashPattern::new(vec![1.], 0.);
+    assert_eq!(pattern.ranges(0., 4.), vec![(0., 1.), (2., 3.)]);
+}
+
+#[test]
+fn test_dash_ranges_limit() {
+    // Too many dashes to draw, so the line is solid
+    let pattern = DashPattern::dashed(0.01, 0.01);
+    assert_eq!(pattern.ranges(0., 100000.), vec![(0., 100000.)]);
+
+    // The period is lost in the precision of the distances
+    let pattern = DashPattern::dashed(0.002, 0.002);
+    assert!(pattern.ranges(1e6, 1e6 + 10.).len() <= 2500);
+}
@@ -0,0 +1,82 @@
+use std::fmt;
+
+/// Why a line can not be drawn
+#[derive(Clone, Debug, PartialEq)]
+pub enum FlexLineError {
+    /// Lines need at least 2 locations
+    TooFewLocations(usize),
+    /// The location at the index is NaN or infinite
+    InvalidLocation(usize),
+    /// A width is negative, NaN or infinite
+    InvalidWidth(f32),
+    /// `widths` does not have a width for each location
+    WidthCount {
+        expected: usize,
+        found: usize,
+    },
+    /// [`LineColor::PerVertex`](crate::LineColor::PerVertex) does not have a color for each location
+    ColorCount {
+        expected: usize,
+        found: usize,
+    },
+    /// A corner radius is negative, NaN or infinite, or a miter limit is below 1, NaN or infinite
+    InvalidCornerStyle,
+    /// The offset of [`Alignment::Offset`](crate::Alignment::Offset) is NaN or infinite
+    InvalidOffset(f32),
+    /// The feathering width is negative, NaN or infinite
+    InvalidFeathering(f32),
+    /// The width of the border is negative, NaN or infinite
+    InvalidBorder(f32),
+    /// The width fraction of the band at the index is negative, NaN or infinite
+    InvalidBand(usize),
+    /// The scale of a start or end marker is negative, NaN or infinite
+    InvalidMarkerScale(f32),
+    /// A key of the width profile is not finite or has a negative factor, or the tolerance of a profile function is not a finite, positive number
+    InvalidWidthProfile,
+    /// The tolerance of the smoothing is not a finite, positive number
+    InvalidSmoothing(f32),
+    /// A point of a [`CapStyle::Custom`](crate::CapStyle::Custom) outline is NaN or infinite
+    InvalidCapStyle,
+    /// A gradient has stops that are not finite or not in order
+    InvalidGradient,
+    /// The direction of a fill gradient is zero, NaN or infinite, or a hole has a point that is not finite
+    InvalidFill,
+    /// A dash or gap length is negative, NaN or infinite
+    InvalidDashLength(f32),
+    /// The lengths of a dash pattern do not add up to a positive length
+    EmptyDashPattern,
+    /// The offset of a dash pattern is NaN or infinite
+    InvalidDashOffset(f32),
+    /// The length of [`UvMode::Repeat`](crate::UvMode::Repeat) is zero, negative, NaN or infinite,
+    /// or the line has no width to repeat [`UvMode::Normalized`](crate::UvMode::Normalized) over
+    InvalidUvLength(f32),
+}
+
+impl fmt::Display for FlexLineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FlexLineError::TooFewLocations(count) => write!(f, "line has {count} locations, but needs at least 2"),
+            FlexLineError::InvalidLocation(index) => write!(f, "location {index} is not finite"),
+            FlexLineError::InvalidWidth(width) => write!(f, "width {width} is not a finite, positive number"),
+            FlexLineError::WidthCount { expected, found } => write!(f, "expected {expected} widths, found {found}"),
+            FlexLineError::ColorCount { expected, found } => write!(f, "expected {expected} vertex colors, found {found}"),
+            FlexLineError::InvalidCornerStyle => write!(f, "corner radius is not a finite, positive number, or miter limit is not a finite number of at least 1"),
+            FlexLineError::InvalidOffset(offset) => write!(f, "alignment offset {offset} is not finite"),
+            FlexLineError::InvalidFeathering(feathering) => write!(f, "feathering width {feathering} is not a finite, positive number"),
+            FlexLineError::InvalidBorder(width) => write!(f, "border width {width} is not a finite, positive number"),
+            FlexLineError::InvalidBand(index) => write!(f, "width fraction of band {index} is not a finite, positive number"),
+            FlexLineError::InvalidMarkerScale(scale) => write!(f, "marker scale {scale} is not a finite, positive number"),
+            FlexLineError::InvalidWidthProfile => write!(f, "width profile keys are not finite and positive, or its tolerance is not a finite, positive number"),
+            FlexLineError::InvalidSmoothing(tolerance) => write!(f, "smoothing tolerance {tolerance} is not a finite, positive number"),
+            FlexLineError::InvalidCapStyle => write!(f, "custom cap outline is not finite"),
+            FlexLineError::InvalidGradient => write!(f, "gradient stops are not finite and in increasing order"),
+            FlexLineError::InvalidFill => write!(f, "fill gradient direction is zero or not finite, or a hole is not finite"),
+            FlexLineError::InvalidDashLength(length) => write!(f, "dash or gap length {length} is not a finite, positive number"),
+            FlexLineError::EmptyDashPattern => write!(f, "dash pattern has no dashes or gaps of positive length"),
+            FlexLineError::InvalidDashOffset(offset) => write!(f, "dash offset {offset} is not finite"),
+            FlexLineError::InvalidUvLength(length) => write!(f, "UV repeat length {length} is not a finite, positive number"),
+        }
+    }
+}
+
+impl std::error::Error for FlexLineError {}
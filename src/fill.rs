@@ -0,0 +1,195 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::sweep::{self, Edge};
+
+/// Inside of a connected line, drawn under it in the same mesh.
+/// The fill follows the locations, and the line covers its edge.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fill {
+    pub color: FillColor,
+    /// More contours of the shape, like holes and islands, in the same coordinates as the locations.
+    /// They are not outlined by the line, but can get lines of their own.
+    pub holes: Vec<Vec<Vec2>>,
+    /// Which parts of the contours are inside, where they overlap or cross themselves
+    pub rule: FillRule,
+}
+
+/// Which points are inside a shape, from the number of times its contours go around them, as in SVG
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FillRule {
+    /// Points the contours go around at all, in either direction.
+    /// Holes must go around the other way than the contour around them.
+    NonZero,
+    /// Points the contours go around an odd number of times, so holes can go either way
+    EvenOdd,
+}
+
+impl FillRule {
+    fn is_inside(&self, winding: i32) -> bool {
+        match self {
+            FillRule::NonZero => winding != 0,
+            FillRule::EvenOdd => winding % 2 != 0,
+        }
+    }
+}
+
+/// Color of a [`Fill`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum FillColor {
+    Solid(Color),
+    /// Linear gradient across the shape, from `start` at its furthest point against the direction to `end` at its furthest point along it
+    Gradient {
+        direction: Vec2,
+        start: Color,
+        end: Color,
+    },
+}
+
+impl Fill {
+    pub fn new(color: Color) -> Self {
+        Fill { color: FillColor::Solid(color), holes: Vec::new(), rule: FillRule::NonZero }
+    }
+
+    pub fn gradient(direction: Vec2, start: Color, end: Color) -> Self {
+        Fill { color: FillColor::Gradient { direction, start, end }, holes: Vec::new(), rule: FillRule::NonZero }
+    }
+
+    pub fn with_hole(mut self, hole: Vec<Vec2>) -> Self {
+        self.holes.push(hole);
+        self
+    }
+
+    pub fn with_rule(mut self, rule: FillRule) -> Self {
+        self.rule = rule;
+        self
+    }
+
+    pub(crate) fn is_valid(&self) -> bool {
+        let valid_color = match self.color {
+            FillColor::Solid(_) => true,
+            FillColor::Gradient { direction, .. } => direction.is_finite() && direction != Vec2::ZERO,
+        };
+        valid_color && self.holes.iter().flatten().all(|point| point.is_finite())
+    }
+
+    /// Triangles covering the inside of the outline and the holes, with the points they are made of.
+    pub(crate) fn triangulate(&self, outline: &[Vec2]) -> (Vec<Vec2>, Vec<[usize; 3]>) {
+        let contours = std::iter::once(outline).chain(self.holes.iter().map(Vec::as_slice));
+        let edges: Vec<Edge> = contours.flat_map(|contour| {
+            (0..contour.len()).map(|i| Edge { from: contour[i], to: contour[(i + 1) % contour.len()], shape: 0 })
+        }).collect();
+        if self.holes.is_empty() && !sweep::any_crossing(&edges) {
+            // Both rules fill all of a simple polygon
+            return (outline.to_vec(), ear_clip(outline));
+        }
+
+        // Triangles from the sweep share their corners with their neighbors
+        let mut points = Vec::new();
+        let mut indices: HashMap<[u32; 2], usize> = HashMap::new();
+        let triangles = sweep::fill(&edges, |winding| self.rule.is_inside(winding)).into_iter().map(|triangle| {
+            triangle.map(|point| *indices.entry(point.to_array().map(f32::to_bits)).or_insert_with(|| {
+                points.push(point);
+                points.len() - 1
+            }))
+        }).collect();
+        (points, triangles)
+    }
+
+    /// The color at each of the points of the shape
+    pub(crate) fn colors(&self, points: &[Vec2]) -> Vec<[f32; 4]> {
+        let to_array = |color: Srgba| [color.red, color.green, color.blue, color.alpha];
+        match &self.color {
+            FillColor::Solid(color) => vec![to_array(color.to_srgba()); points.len()],
+            FillColor::Gradient { direction, start, end } => {
+                let along: Vec<f32> = points.iter().map(|point| point.dot(*direction)).collect();
+                let min = along.iter().copied().fold(f32::INFINITY, f32::min);
+                let max = along.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+                along.iter().map(|along| {
+                    let t = if max > min { (along - min) / (max - min) } else { 0. };
+                    to_array(start.to_srgba().mix(&end.to_srgba(), t))
+                }).collect()
+            },
+        }
+    }
+}
+
+/// Triangles covering the inside of a polygon that does not cross itself, as indices of its points.
+/// The triangles are cut off the polygon one corner at a time, at corners with no other points inside.
+fn ear_clip(points: &[Vec2]) -> Vec<[usize; 3]> {
+    let area: f32 = (0..points.len()).map(|i| points[i].perp_dot(points[(i + 1) % points.len()])).sum();
+    // Going counterclockwise, the corners to cut off turn left
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    if area < 0. {
+        remaining.reverse();
+    }
+
+    let mut triangles = Vec::new();
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let corner = |i: usize| [remaining[(i + count - 1) % count], remaining[i], remaining[(i + 1) % count]];
+        let turn = |[a, b, c]: [usize; 3]| (points[b] - points[a]).perp_dot(points[c] - points[b]);
+        let is_ear = |i: usize| {
+            let [a, b, c] = corner(i);
+            let triangle = [points[a], points[b], points[c]];
+            turn([a, b, c]) > 0. && !remaining.iter().any(|p| !triangle.contains(&points[*p]) && in_triangle(points[*p], triangle))
+        };
+
+        // Polygons that cross themselves might have no ears left, so a corner is cut off anyway
+        let i = (0..count).find(|i| is_ear(*i))
+            .or_else(|| (0..count).find(|i| turn(corner(*i)) > 0.))
+            .unwrap_or(0);
+        triangles.push(corner(i));
+        remaining.remove(i);
+    }
+    if let [a, b, c] = remaining[..] {
+        triangles.push([a, b, c]);
+    }
+    triangles
+}
+
+/// Whether the point is inside the counterclockwise triangle, or on its edges
+fn in_triangle(point: Vec2, [a, b, c]: [Vec2; 3]) -> bool {
+    (b - a).perp_dot(point - a) >= 0. && (c - b).perp_dot(point - b) >= 0. && (a - c).perp_dot(point - c) >= 0.
+}
+
+#[test]
+fn test_ear_clip() {
+    // An L shape, going clockwise, which has a corner that cannot be cut off
+    let points = [Vec2::new(0., 0.), Vec2::new(0., 2.), Vec2::new(1., 2.), Vec2::new(1., 1.), Vec2::new(2., 1.), Vec2::new(2., 0.)];
+    let triangles = ear_clip(&points);
+    assert_eq!(triangles.len(), 4);
+
+    let area: f32 = triangles.iter().map(|[a, b, c]| (points[*b] - points[*a]).perp_dot(points[*c] - points[*a]) / 2.).sum();
+    assert!((area - 3.).abs() < 1e-5);
+    assert!(triangles.iter().all(|[a, b, c]| (points[*b] - points[*a]).perp_dot(points[*c] - points[*a]) > 0.));
+}
+
+#[test]
+fn test_fill_rules() {
+    let area = |fill: &Fill, outline: &[Vec2]| {
+        let (points, triangles) = fill.triangulate(outline);
+        triangles.iter().map(|[a, b, c]| (points[*b] - points[*a]).perp_dot(points[*c] - points[*a]).abs() / 2.).sum::<f32>()
+    };
+    let square = |min: f32, max: f32| vec![Vec2::new(min, min), Vec2::new(max, min), Vec2::new(max, max), Vec2::new(min, max)];
+    let reversed = |mut points: Vec<Vec2>| {
+        points.reverse();
+        points
+    };
+
+    // A hole going the other way is cut out by both rules
+    let fill = Fill::new(Color::WHITE).with_hole(reversed(square(1., 3.)));
+    assert!((area(&fill, &square(0., 4.)) - 12.).abs() < 1e-4);
+    assert!((area(&fill.with_rule(FillRule::EvenOdd), &square(0., 4.)) - 12.).abs() < 1e-4);
+
+    // A hole going the same way is only cut out by the even-odd rule
+    let fill = Fill::new(Color::WHITE).with_hole(square(1., 3.));
+    assert!((area(&fill, &square(0., 4.)) - 16.).abs() < 1e-4);
+    assert!((area(&fill.with_rule(FillRule::EvenOdd), &square(0., 4.)) - 12.).abs() < 1e-4);
+
+    // A pentagram, which goes around its middle twice
+    let star: Vec<Vec2> = (0..5).map(|i| Vec2::from_angle(i as f32 * 4. * std::f32::consts::PI / 5.)).collect();
+    let non_zero = area(&Fill::new(Color::WHITE), &star);
+    let even_odd = area(&Fill::new(Color::WHITE).with_rule(FillRule::EvenOdd), &star);
+    assert!((non_zero - 1.1226).abs() < 1e-3);
+    assert!((even_odd - 0.7757).abs() < 1e-3);
+}
@@ -1,27 +1,139 @@
-use std::f32::consts::PI;
+use std::{borrow::Cow, f32::consts::PI};
 
-use bevy::{prelude::*, render::{mesh::{self, PrimitiveTopology}, render_asset::RenderAssetUsages}};
+use bevy::prelude::*;
 
-use crate::vector_utils::*;
+use crate::{band::Band, border::Border, dash::DashPattern, error::FlexLineError, fill::Fill, gradient::{Gradient, StopUnits}, marker::Marker, profile::WidthProfile, spline::Smoothing, stroke::*, vector_utils::*};
 
 #[derive(Clone, Component)]
 pub struct FlexLine {
     pub locations: Vec<Vec2>,
     pub width: f32,
+    /// Width at each location, used instead of `width`
+    pub widths: Option<Vec<f32>>,
+    /// Scales the width along the length of the line
+    pub width_profile: Option<WidthProfile>,
+    /// Treat the locations as control points of a smooth curve
+    pub smoothing: Option<Smoothing>,
     pub corner_style: CornerStyle,
     pub alignment: Alignment,
     pub connection_style: ConnectionStyle,
     pub color: LineColor,
+    /// Draw the line as dashes instead of a continuous stroke
+    pub dash_pattern: Option<DashPattern>,
+    /// Cap at the first location, and at the start of each dash
+    pub start_cap: CapStyle,
+    /// Cap at the last location, and at the end of each dash
+    pub end_cap: CapStyle,
+    /// Marker at the first location of unconnected lines
+    pub start_marker: Option<Marker>,
+    /// Marker at the last location of unconnected lines
+    pub end_marker: Option<Marker>,
+    /// How texture coordinates follow the line
+    pub uv_mode: UvMode,
+    /// Cover each point only once, so translucent lines do not get darker where they overlap themselves.
+    /// This makes the mesh larger and slower to build.
+    pub merge_overlaps: bool,
+    /// Fade the edges out over this width in world units, for smooth edges without MSAA.
+    /// With the default 2D camera a world unit is a pixel, so zoomed cameras need the width scaled to match.
+    pub feathering_width: Option<f32>,
+    /// Band of another color along the sides
+    pub border: Option<Border>,
+    /// Bands across the line from the left side to the right side, each with its own color or dashes
+    pub bands: Vec<Band>,
+    /// Fill the inside of connected lines
+    pub fill: Option<Fill>,
 }
 
 #[derive(Clone, Copy)]
 pub enum CornerStyle {
+    /// Miter join without a limit. The sides are extended until they meet
     Sharp,
+    /// Fillet, where the inner side follows an arc with the given radius.
+    /// The radius is reduced where the segments are too short for the arc.
     Rounded {
         radius: f32,
         /// Number of vertices in a circle. A number proportional to the arc is used
         resolution: usize,
     },
+    /// Miter join, that falls back to a bevel when the miter length exceeds `limit` times the width.
+    /// The miter is never shorter than the width, so the limit is at least 1.
+    Miter {
+        limit: f32,
+    },
+    /// The outer side is cut off straight between the ends of the sides
+    Bevel,
+    /// The outer side follows an arc around the location
+    Round {
+        /// Number of vertices in a circle. A number proportional to the arc is used
+        resolution: usize,
+    },
+}
+
+/// Shape of the ends of an unconnected line
+#[derive(Clone, PartialEq)]
+pub enum CapStyle {
+    /// The line ends flat at the location
+    Butt,
+    /// The line ends flat, half the width beyond the location
+    Square,
+    /// Half circle around the location
+    Round {
+        /// Number of vertices in a circle
+        resolution: usize,
+    },
+    /// Point half the width beyond the location
+    Triangle,
+    /// Outline going around the end, from the left side to the right side.
+    /// X points forward and Y to the left, both in units of half the width.
+    /// The outline must be visible from the origin, as it is drawn as a fan from there.
+    Custom(Vec<Vec2>),
+}
+
+impl CapStyle {
+    /// The outline of the cap, in the same coordinates as [`CapStyle::Custom`]
+    pub(crate) fn outline(&self) -> Vec<Vec2> {
+        match self {
+            CapStyle::Butt => Vec::new(),
+            CapStyle::Square => vec![Vec2::new(0., 1.), Vec2::new(1., 1.), Vec2::new(1., -1.), Vec2::new(0., -1.)],
+            CapStyle::Round { resolution } => {
+                let steps = 2.max(resolution / 2);
+                (0..steps + 1).map(|i| {
+                    let angle = i as f32 * PI / steps as f32;
+                    Vec2::new(angle.sin(), angle.cos())
+                }).collect()
+            },
+            CapStyle::Triangle => vec![Vec2::new(0., 1.), Vec2::new(1., 0.), Vec2::new(0., -1.)],
+            CapStyle::Custom(outline) => outline.clone(),
+        }
+    }
+}
+
+/// How the U texture coordinate follows the length of the line.
+/// V goes from 0 on the left side to 1 on the right side.
+#[derive(Clone, Copy, PartialEq)]
+pub enum UvMode {
+    /// The texture is stretched once over the whole line
+    Stretch,
+    /// The texture repeats every `length` world units
+    Repeat {
+        length: f32,
+    },
+    /// The texture repeats every width of the line, so it keeps its aspect ratio.
+    /// Where the width changes along the line, the widest width is used.
+    Normalized,
+}
+
+impl UvMode {
+    /// U at the distance along a line with the given length and widest width
+    pub(crate) fn u(&self, distance: f32, length: f32, width: f32) -> f32 {
+        match self {
+            UvMode::Stretch => distance / length,
+            UvMode::Repeat { length } => distance / length,
+            // A width profile can still take the width down to nothing
+            UvMode::Normalized if width <= 0. => 0.,
+            UvMode::Normalized => distance / width,
+        }
+    }
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -47,11 +159,20 @@ pub enum LineColor {
         right: Color,
     },
     PerVertex(Vec<Color>),
+    /// Gradient across the width with any number of stops, from 0 on the left side to 1 on the right side
+    GradientAcrossStops(Gradient),
+    /// Gradient along the length of the line. Connected lines end where they start, at the full length.
+    GradientAlong {
+        gradient: Gradient,
+        units: StopUnits,
+    },
 }
 
 impl LineColor {
+    /// param: position along the locations, as a fractional index.
+    /// distance: distance along a line with the given length.
     /// gradient: 1 for right side, -1 for left side
-    fn get(&self, index: usize, gradient: f32) -> [f32; 4] {
+    pub(crate) fn get(&self, param: f32, distance: f32, length: f32, gradient: f32) -> [f32; 4] {
         match self {
             LineColor::Fill(color) => {
                 let color = color.to_srgba();
@@ -63,13 +184,46 @@ impl LineColor {
                 [color.red, color.green, color.blue, color.alpha]
             },
             LineColor::PerVertex(vertex_colors) => {
-                let color = vertex_colors[index].to_srgba();
+                let index = param.floor() as usize;
+                let color = vertex_color(vertex_colors, index).mix(&vertex_color(vertex_colors, index + 1), param.fract());
+                [color.red, color.green, color.blue, color.alpha]
+            },
+            LineColor::GradientAcrossStops(stops) => {
+                let color = stops.color_at((gradient + 1.) / 2.);
                 [color.red, color.green, color.blue, color.alpha]
             },
+            LineColor::GradientAlong { gradient, units } => {
+                let color = gradient.color_at(units.position(distance, length));
+                [color.red, color.green, color.blue, color.alpha]
+            },
+        }
+    }
+
+    /// Positions across the line, from -1 to 1, where the color needs vertices to blend right
+    pub(crate) fn stops_across(&self) -> Vec<f32> {
+        match self {
+            LineColor::GradientAcrossStops(gradient) => gradient.stops_between(0., 1.).into_iter().map(|position| position * 2. - 1.).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Distances between `from` and `to` where the color needs vertices to blend right
+    pub(crate) fn stops_between(&self, from: f32, to: f32, length: f32) -> Vec<f32> {
+        match self {
+            LineColor::GradientAlong { gradient, units } => gradient.stops_between(units.position(from, length), units.position(to, length))
+                .into_iter()
+                .map(|position| units.distance(position, length))
+                .collect(),
+            _ => Vec::new(),
         }
     }
 }
 
+/// The color at the index, wrapping around. White if there are no colors
+fn vertex_color(colors: &[Color], index: usize) -> Srgba {
+    colors.get(index % colors.len().max(1)).map_or(Srgba::WHITE, |color| color.to_srgba())
+}
+
 impl Alignment {
     fn left_width(&self, width: f32) -> f32 {
         match self {
@@ -95,15 +249,30 @@ impl Default for FlexLine {
         FlexLine {
             locations: Vec::new(),
             width: 1.,
+            widths: None,
+            width_profile: None,
+            smoothing: None,
             corner_style: CornerStyle::Sharp,
             alignment: Alignment::Center,
             connection_style: ConnectionStyle::Connected,
             color: LineColor::Fill(Color::WHITE),
+            dash_pattern: None,
+            start_cap: CapStyle::Butt,
+            end_cap: CapStyle::Butt,
+            start_marker: None,
+            end_marker: None,
+            uv_mode: UvMode::Stretch,
+            merge_overlaps: false,
+            feathering_width: None,
+            border: None,
+            bands: Vec::new(),
+            fill: None,
         }
     }
 }
 
 impl FlexLine {
+    /// Make a line, which might not be valid. See [`FlexLine::try_new`].
     pub fn new(
         locations: Vec<Vec2>, 
         width: f32, 
@@ -112,32 +281,199 @@ impl FlexLine {
         connection_style: ConnectionStyle,
         color: LineColor
     ) -> Self {
-        assert!(locations.len() >= 2, "FlexPath must have at least 2 locations");
+        // Rounded corners used to come with round caps
+        let cap = match corner_style {
+            CornerStyle::Rounded { resolution, .. } => CapStyle::Round { resolution },
+            _ => CapStyle::Butt,
+        };
         FlexLine {
             locations,
             width,
+            widths: None,
+            width_profile: None,
+            smoothing: None,
             corner_style,
             alignment,
-            connection_style: connection_style,
+            connection_style,
             color,
+            dash_pattern: None,
+            start_cap: cap.clone(),
+            end_cap: cap,
+            start_marker: None,
+            end_marker: None,
+            uv_mode: UvMode::Stretch,
+            merge_overlaps: false,
+            feathering_width: None,
+            border: None,
+            bands: Vec::new(),
+            fill: None,
+        }
+    }
+
+    /// Make a line, checking that it can be drawn
+    pub fn try_new(
+        locations: Vec<Vec2>,
+        width: f32,
+        alignment: Alignment,
+        corner_style: CornerStyle,
+        connection_style: ConnectionStyle,
+        color: LineColor
+    ) -> Result<Self, FlexLineError> {
+        let line = Self::new(locations, width, alignment, corner_style, connection_style, color);
+        line.validate()?;
+        Ok(line)
+    }
+
+    /// Check that the line can be drawn.
+    /// Invalid lines are skipped by the plugin, with a warning.
+    pub fn validate(&self) -> Result<(), FlexLineError> {
+        let count = self.locations.len();
+        if count < 2 {
+            return Err(FlexLineError::TooFewLocations(count));
+        }
+        if let Some(index) = self.locations.iter().position(|location| !location.is_finite()) {
+            return Err(FlexLineError::InvalidLocation(index));
+        }
+
+        let valid_width = |width: f32| width.is_finite() && width >= 0.;
+        if !valid_width(self.width) {
+            return Err(FlexLineError::InvalidWidth(self.width));
+        }
+        if let Some(widths) = &self.widths {
+            if widths.len() != count {
+                return Err(FlexLineError::WidthCount { expected: count, found: widths.len() });
+            }
+            if let Some(width) = widths.iter().find(|width| !valid_width(**width)) {
+                return Err(FlexLineError::InvalidWidth(*width));
+            }
+        }
+
+        if let LineColor::PerVertex(colors) = &self.color {
+            if colors.len() != count {
+                return Err(FlexLineError::ColorCount { expected: count, found: colors.len() });
+            }
+        }
+        if let LineColor::GradientAcrossStops(gradient) | LineColor::GradientAlong { gradient, .. } = &self.color {
+            if !gradient.is_valid() {
+                return Err(FlexLineError::InvalidGradient);
+            }
+        }
+
+        let valid_corner = match self.corner_style {
+            CornerStyle::Rounded { radius, .. } => radius.is_finite() && radius >= 0.,
+            CornerStyle::Miter { limit } => limit.is_finite() && limit >= 1.,
+            _ => true,
+        };
+        if !valid_corner {
+            return Err(FlexLineError::InvalidCornerStyle);
+        }
+        for cap in [&self.start_cap, &self.end_cap] {
+            if let CapStyle::Custom(outline) = cap {
+                if !outline.iter().all(|point| point.is_finite()) {
+                    return Err(FlexLineError::InvalidCapStyle);
+                }
+            }
+        }
+        if let Some(marker) = [&self.start_marker, &self.end_marker].into_iter().flatten().find(|marker| !valid_width(marker.scale)) {
+            return Err(FlexLineError::InvalidMarkerScale(marker.scale));
+        }
+        if self.width_profile.as_ref().is_some_and(|profile| !profile.is_valid()) {
+            return Err(FlexLineError::InvalidWidthProfile);
+        }
+        if let Some(tolerance) = self.smoothing.as_ref().map(Smoothing::tolerance).filter(|tolerance| !tolerance.is_finite() || *tolerance <= 0.) {
+            return Err(FlexLineError::InvalidSmoothing(tolerance));
+        }
+        if let Alignment::Offset(offset) = self.alignment {
+            if !offset.is_finite() {
+                return Err(FlexLineError::InvalidOffset(offset));
+            }
+        }
+        match self.uv_mode {
+            UvMode::Repeat { length } if !length.is_finite() || length <= 0. => return Err(FlexLineError::InvalidUvLength(length)),
+            UvMode::Normalized if self.widest() <= 0. => return Err(FlexLineError::InvalidUvLength(0.)),
+            _ => {},
+        }
+        if let Some(feathering) = self.feathering_width.filter(|feathering| !valid_width(*feathering)) {
+            return Err(FlexLineError::InvalidFeathering(feathering));
+        }
+        if let Some(border) = self.border.filter(|border| !valid_width(border.width)) {
+            return Err(FlexLineError::InvalidBorder(border.width));
+        }
+        if let Some(index) = self.bands.iter().position(|band| !valid_width(band.fraction)) {
+            return Err(FlexLineError::InvalidBand(index));
+        }
+        if self.fill.as_ref().is_some_and(|fill| !fill.is_valid()) {
+            return Err(FlexLineError::InvalidFill);
+        }
+        let patterns = self.dash_pattern.iter().chain(self.bands.iter().filter_map(|band| band.dash_pattern.as_ref()));
+        for pattern in patterns {
+            pattern.validate()?;
+        }
+        Ok(())
+    }
+
+    /// The largest width at any of the locations
+    fn widest(&self) -> f32 {
+        (0..self.locations.len()).map(|i| self.width_at(i)).fold(0., f32::max)
+    }
+
+    fn width_at(&self, index: usize) -> f32 {
+        match &self.widths {
+            Some(widths) => widths.get(index).copied().unwrap_or(self.width),
+            None => self.width,
         }
     }
 
-    fn left_width(&self) -> f32 {
-        self.alignment.left_width(self.width)
+    fn left_width(&self, index: usize) -> f32 {
+        self.alignment.left_width(self.width_at(index))
     }
 
-    fn right_width(&self) -> f32 {
-        self.alignment.right_width(self.width)
+    fn right_width(&self, index: usize) -> f32 {
+        self.alignment.right_width(self.width_at(index))
     }
 
-    fn is_connected(&self) -> bool {
+    /// Left side of the segment between the locations at the 2 indices
+    fn left_side(&self, from: usize, to: usize) -> (Vec2, Vec2) {
+        calc_left_side_segment(self.locations[from], self.locations[to], self.left_width(from), self.left_width(to))
+    }
+
+    /// Right side of the segment between the locations at the 2 indices
+    fn right_side(&self, from: usize, to: usize) -> (Vec2, Vec2) {
+        calc_right_side_segment(self.locations[from], self.locations[to], self.right_width(from), self.right_width(to))
+    }
+
+    pub(crate) fn is_connected(&self) -> bool {
         match self.connection_style {
             ConnectionStyle::Connected => true,
             ConnectionStyle::Unconnected => false,
         }
     }
 
+    /// Whether the line has no corner with an extent at the location, as at the ends and where it goes straight on or straight back
+    fn is_straight_at(&self, index: usize) -> bool {
+        let (Some(prev_idx), Some(next_idx)) = (self.get_prev_idx(index), self.get_next_idx(index)) else {
+            return true;
+        };
+        let (prev, location, next) = (self.locations[prev_idx], self.locations[index], self.locations[next_idx]);
+        orientation_test(prev, location, next) == Orientation::Straight
+    }
+
+    /// How much of the segment between the 2 locations a corner at `index` can use.
+    /// Segments are shared with the corner at the other end, unless that is straight.
+    fn available_length(&self, index: usize, other: usize) -> f32 {
+        let length = self.locations[index].distance(self.locations[other]);
+        if self.is_straight_at(other) { length } else { length / 2. }
+    }
+
+    /// Whether the intersection of the inner sides of a corner lies within the available part of the segments.
+    /// At sharp turns it lies far away, and the sides would fold back over the neighbouring segments.
+    /// Where the line is offset past the path, they can meet far beyond the location instead.
+    fn inner_fits(&self, inner: Vec2, index: usize, prev_idx: usize, next_idx: usize) -> bool {
+        let location = self.locations[index];
+        let along = |other: usize| (inner - location).dot((self.locations[other] - location).normalize_or_zero()).abs();
+        along(prev_idx) <= self.available_length(index, prev_idx) && along(next_idx) <= self.available_length(index, next_idx)
+    }
+
     fn get_next_idx(&self, idx: usize) -> Option<usize> {
         if self.is_connected() || idx < self.locations.len() - 1 {
             Some((idx + 1) % self.locations.len())
@@ -154,130 +490,357 @@ impl FlexLine {
         }
     }
 
+
+    /// Arc length from the first location to each location
+    pub(crate) fn distances(&self) -> Vec<f32> {
+        let mut distances = Vec::with_capacity(self.locations.len());
+        let mut distance = 0.;
+        for i in 0..self.locations.len() {
+            if i > 0 {
+                distance += self.locations[i].distance(self.locations[i - 1]);
+            }
+            distances.push(distance);
+        }
+        distances
+    }
+
+    /// Total length of the line, including the closing segment if connected
+    pub(crate) fn length(&self, distances: &[f32]) -> f32 {
+        let last = distances.len() - 1;
+        if self.is_connected() {
+            distances[last] + self.locations[last].distance(self.locations[0])
+        } else {
+            distances[last]
+        }
+    }
+
     pub(crate) fn make_mesh(&self) -> Mesh {
-        let mut vertices: Vec<[f32; 3]> = Vec::new();
-        let mut indices: Vec<u32> = Vec::new();
-        let mut colors: Vec<[f32; 4]> = Vec::new();
+        let line = self.resolved();
+        if line.locations.len() < 2 {
+            // All locations are on top of each other
+            return MeshBuffers::default().into_mesh();
+        }
+        line.tessellate()
+    }
+
+    /// The line with smoothing and the width profile applied, by adding locations along the curve and where the width changes.
+    /// Borders outside the line are added to the widths.
+    pub(crate) fn resolved(&self) -> Cow<'_, FlexLine> {
+        let mut line = Cow::Borrowed(self);
+
+        if let Some(smoothing) = &self.smoothing {
+            let samples = smoothing.sample(&self.locations, self.is_connected());
+            let mut smoothed = self.resampled(samples);
+            smoothed.smoothing = None;
+            line = Cow::Owned(smoothed);
+        }
+
+        if let Some(profile) = &self.width_profile {
+            let distances = line.distances();
+            let length = line.length(&distances);
+            let subdivisions: Vec<f32> = profile.subdivisions().iter().map(|p| p * length).collect();
+            let mut profiled = line.subdivided(&distances, &subdivisions);
 
+            let widths = profiled.distances().iter().enumerate()
+                .map(|(i, distance)| profiled.width_at(i) * profile.factor(distance / length))
+                .collect();
+            profiled.widths = Some(widths);
+            profiled.width_profile = None;
+            line = Cow::Owned(profiled);
+        }
+
+        if let Some(border) = self.border.filter(|border| border.added_width() > 0.) {
+            let widths = (0..line.locations.len()).map(|i| line.width_at(i) + border.added_width()).collect();
+            let line = line.to_mut();
+            line.widths = Some(widths);
+            line.width += border.added_width();
+        }
+
+        if let Some(deduplicated) = line.deduplicated() {
+            line = Cow::Owned(deduplicated);
+        }
+        line
+    }
 
+    /// Copy of the line without the locations that are on top of the previous one, if there are any.
+    /// Connected lines also lose the last location if it is on top of the first.
+    fn deduplicated(&self) -> Option<FlexLine> {
+        let same = |a: Vec2, b: Vec2| a.distance(b) <= 1e-6 * a.abs().max_element().max(1.);
+        let mut kept: Vec<usize> = Vec::new();
+        for (i, location) in self.locations.iter().enumerate() {
+            if kept.last().map_or(true, |last| !same(self.locations[*last], *location)) {
+                kept.push(i);
+            }
+        }
         if self.is_connected() {
-            // Add dummy vertices to the beginnig.
-            // These will be replaced by the 2 last vertices at the end
-            vertices.push([0., 0., 0.]);
-            vertices.push([0., 0., 0.]);
-            colors.push(self.color.get(0, -1.));
-            colors.push(self.color.get(0,  1.));
+            while kept.len() > 1 && same(self.locations[kept[0]], self.locations[kept[kept.len() - 1]]) {
+                kept.pop();
+            }
         }
 
-        for i in 0..self.locations.len() {
-            self.add_corner(i, &mut vertices, &mut indices, &mut colors);
+        if kept.len() == self.locations.len() {
+            return None;
         }
-        
-        if !self.is_connected() {
-            self.calc_caps(&mut vertices, &mut indices, &mut colors);
+        Some(self.resampled(kept.into_iter().map(|i| (i, 0., self.locations[i]))))
+    }
+
+    /// Copy of the line with locations added at the given distances, which must be sorted.
+    fn subdivided(&self, distances: &[f32], at: &[f32]) -> FlexLine {
+        let mut samples = Vec::new();
+        let mut next_at = 0;
+        for (i, (location, from)) in self.locations.iter().zip(distances).enumerate() {
+            samples.push((i, 0., *location));
+
+            let Some(next) = self.get_next_idx(i) else {
+                break;
+            };
+            let (from, to) = (*from, from + location.distance(self.locations[next]));
+
+            while next_at < at.len() && at[next_at] < to {
+                let t = (at[next_at] - from) / (to - from);
+                next_at += 1;
+                if t > 0. {
+                    samples.push((i, t, location.lerp(self.locations[next], t)));
+                }
+            }
+        }
+        self.resampled(samples)
+    }
+
+    /// Copy of the line with new locations. Each is given by the index of the location it comes after,
+    /// how far it is towards the next location, and its position.
+    /// Widths and colors of the new locations are interpolated.
+    pub(crate) fn resampled(&self, samples: impl IntoIterator<Item = (usize, f32, Vec2)>) -> FlexLine {
+        let mut locations = Vec::new();
+        let mut widths = Vec::new();
+        let mut colors = Vec::new();
+        let vertex_colors = match &self.color {
+            LineColor::PerVertex(vertex_colors) => Some(vertex_colors),
+            _ => None,
+        };
+
+        for (i, t, location) in samples {
+            let next = self.get_next_idx(i).unwrap_or(i);
+            locations.push(location);
+            widths.push(self.width_at(i) + (self.width_at(next) - self.width_at(i)) * t);
+            if let Some(vertex_colors) = vertex_colors {
+                colors.push(Color::Srgba(vertex_color(vertex_colors, i).mix(&vertex_color(vertex_colors, next), t)));
+            }
+        }
+
+        FlexLine {
+            locations,
+            widths: Some(widths),
+            color: match vertex_colors {
+                Some(_) => LineColor::PerVertex(colors),
+                None => self.color.clone(),
+            },
+            ..self.clone()
+        }
+    }
+
+    fn tessellate(&self) -> Mesh {
+        let mut buffers = MeshBuffers::default();
+
+        let distances = self.distances();
+        let style = VertexStyle {
+            color: &self.color,
+            uv_mode: self.uv_mode,
+            length: self.length(&distances),
+            width: self.widest(),
+            feathering: self.feathering_width.unwrap_or(0.),
+            border: self.border,
+            bands: &self.bands,
+        };
+        if let (Some(fill), true) = (&self.fill, self.is_connected()) {
+            // Under the line
+            buffers.add_fill(&self.locations, fill);
+        }
+
+        let mut strip = Strip::default();
+        for (i, distance) in distances.iter().enumerate() {
+            self.add_corner(i, *distance, &mut strip.sections);
+        }
+
+        if self.is_connected() {
+            // Close the loop by repeating the first section at the end
+            let mut closing = strip.sections[0];
+            closing.distance += self.length(&distances);
+            closing.param += self.locations.len() as f32;
+            strip.sections.push(closing);
+            strip.closed = true;
+        }
+
+        let length = self.length(&distances);
+        if self.is_connected() {
+            let strips = match &self.dash_pattern {
+                Some(pattern) => self.make_loop_dashes(&strip, pattern, length),
+                None => vec![(strip, false)],
+            };
+
+            for (mut strip, open) in strips {
+                if open {
+                    self.add_caps(&mut strip, true, true);
+                }
+                buffers.add_strip(&strip, &style);
+            }
         } else {
-            // Replace the dummy vertices with the last 2 vertices
-            vertices[1] = vertices.pop().unwrap();
-            vertices[0] = vertices.pop().unwrap();
-            colors[1] = colors.pop().unwrap();
-            colors[0] = colors.pop().unwrap();
-            
-            // Replace instances of the last 2 vertices in the indices
-            let index_count = indices.len();
-            if let CornerStyle::Rounded { .. } = self.corner_style {
-                indices[index_count - 4] = 0;
-                indices[index_count - 2] = 1;
-                indices[index_count - 1] = 0;
+            // Leave room for the markers
+            let last = self.locations.len() - 1;
+            let from = self.start_marker.as_ref().map_or(0., |marker| marker.setback(self.width_at(0)));
+            let to = length - self.end_marker.as_ref().map_or(0., |marker| marker.setback(self.width_at(last)));
+
+            let ranges = match &self.dash_pattern {
+                Some(pattern) => pattern.ranges(from, to),
+                None if from <= to => vec![(from, to)],
+                None => Vec::new(),
+            };
+
+            for (dash_from, dash_to) in ranges {
+                let mut dash = strip.slice(dash_from, dash_to);
+                // The markers replace the caps
+                let start_cap = self.start_marker.is_none() || dash_from > from;
+                let end_cap = self.end_marker.is_none() || dash_to < to;
+                self.add_caps(&mut dash, start_cap, end_cap);
+                buffers.add_strip(&dash, &style);
+            }
+
+            if let Some(marker) = &self.start_marker {
+                buffers.add_strip(&marker_strip(&strip.sections[0].flipped(), marker, self.width_at(0)), &style);
+            }
+            if let Some(marker) = &self.end_marker {
+                buffers.add_strip(&marker_strip(strip.sections.last().unwrap(), marker, self.width_at(last)), &style);
             }
         }
 
-        // The other color styles built along with the mesh
-        if let LineColor::Fill(color) = self.color {
-            colors = vec![[
-                    color.to_srgba().red, 
-                    color.to_srgba().green, 
-                    color.to_srgba().blue, 
-                    color.to_srgba().alpha]; 
-                vertices.len()];
+        if self.merge_overlaps {
+            buffers.merge_overlaps();
+        }
+        buffers.into_mesh()
+    }
+
+    /// Cut a connected strip into dashes. Returns the dashes, and whether they are open ended.
+    fn make_loop_dashes(&self, strip: &Strip, pattern: &DashPattern, length: f32) -> Vec<(Strip, bool)> {
+        // The pattern starts at the first location, which might be inside a rounded corner.
+        // Unroll the loop so dashes can continue across the seam.
+        let strip = strip.unrolled(length, self.locations.len());
+        let mut ranges = pattern.ranges(0., length);
+
+        if let [(first_from, first_to), .., (last_from, last_to)] = ranges[..] {
+            if first_from <= 0. && last_to >= length {
+                // The first and last dash meet at the seam
+                ranges.pop();
+                ranges[0] = (last_from, first_to + length);
+            }
+        } else if let [(from, to)] = ranges[..] {
+            if from <= 0. && to >= length {
+                let mut whole = strip.slice(0., length);
+                whole.closed = true;
+                return vec![(whole, false)];
+            }
         }
 
-        return Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
-            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, vertices)
-            .with_inserted_indices(mesh::Indices::U32(indices))
-            .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+        ranges.into_iter().map(|(from, to)| (strip.slice(from, to), true)).collect()
     }
 
     fn add_corner(&self, 
         index: usize, 
-        vertices: &mut Vec<[f32; 3]>,
-        indices: &mut Vec<u32>,
-        colors: &mut Vec<[f32; 4]>
+        distance: f32,
+        sections: &mut Vec<Section>
     ) {
         let location = self.locations[index];
         let Some(prev_idx) = self.get_prev_idx(index) else {
-            // First 2 vertices
+            // First section
             let next = self.locations[index + 1];
-            let left_vert = calc_left_side_segment(location, next, self.left_width()).0;
-            let right_vert = calc_right_side_segment(location, next, self.right_width()).0;
-            vertices.push([left_vert.x, left_vert.y, 0.]);
-            vertices.push([right_vert.x, right_vert.y, 0.]);
-            colors.push(self.color.get(index, -1.));
-            colors.push(self.color.get(index,  1.));
-
+            let left_vert = self.left_side(index, index + 1).0;
+            let right_vert = self.right_side(index, index + 1).0;
+            sections.push(self.straight_section(left_vert, right_vert, next - location, distance, index));
             return;
         };
 
         let Some(next_idx) = self.get_next_idx(index) else {
-            // Last 2 vertices
+            // Last section
             let prev = self.locations[index - 1];
-            let left_vert = calc_left_side_segment(prev, location, self.left_width()).1;
-            let right_vert = calc_right_side_segment(prev, location, self.right_width()).1;
-            vertices.push([left_vert.x, left_vert.y, 0.]);
-            vertices.push([right_vert.x, right_vert.y, 0.]);
-            colors.push(self.color.get(index, -1.));
-            colors.push(self.color.get(index,  1.));
-
-            let a = (vertices.len() - 4) as u32;
-            let b = a + 1;
-            let c = a + 2;
-            let d = a + 3;
-            self.add_quad_indices(indices, a, b, c, d);
-
+            let left_vert = self.left_side(index - 1, index).1;
+            let right_vert = self.right_side(index - 1, index).1;
+            sections.push(self.straight_section(left_vert, right_vert, location - prev, distance, index));
             return;
         };
 
-        let location = self.locations[index];
         let prev = self.locations[prev_idx];
         let next = self.locations[next_idx];
 
+        if is_hairpin(prev, location, next) {
+            // There is no miter or fillet when turning straight back, so pivot around the location instead
+            let steps = match self.corner_style {
+                CornerStyle::Rounded { resolution, .. } | CornerStyle::Round { resolution } => 1.max(resolution / 2),
+                _ => 1,
+            };
+            self.add_pivot_corner(sections, index, prev_idx, next_idx, distance, steps);
+            return;
+        }
+
         match self.corner_style {
-            CornerStyle::Sharp => self.add_sharp_corner(vertices, indices, colors, index, prev_idx, next_idx),
-            CornerStyle::Rounded { radius, resolution } => self.add_rounded_corner(vertices, indices, colors, index, location, prev, next, radius, resolution),
+            CornerStyle::Sharp => self.add_sharp_corner(sections, index, prev_idx, next_idx, distance),
+            CornerStyle::Rounded { radius, resolution } => self.add_rounded_corner(sections, index, prev_idx, next_idx, distance, radius, resolution),
+            CornerStyle::Miter { limit } => {
+                // The ratio between the miter length and the width is 1 / sin(θ/2), θ being the angle between the segments
+                let turn = (location - prev).angle_between(next - location);
+                if 1. / (turn.abs() / 2.).cos() > limit {
+                    self.add_pivot_corner(sections, index, prev_idx, next_idx, distance, 1);
+                } else {
+                    self.add_sharp_corner(sections, index, prev_idx, next_idx, distance);
+                }
+            },
+            CornerStyle::Bevel => self.add_pivot_corner(sections, index, prev_idx, next_idx, distance, 1),
+            CornerStyle::Round { resolution } => {
+                let turn = (location - prev).angle_between(next - location);
+                let steps = 1.max((resolution as f32 / (2. * PI) * turn.abs()).ceil() as usize);
+                self.add_pivot_corner(sections, index, prev_idx, next_idx, distance, steps);
+            },
         }
     }
 
+    fn straight_section(&self, left: Vec2, right: Vec2, direction: Vec2, distance: f32, index: usize) -> Section {
+        Section {
+            left,
+            mid: left.midpoint(right),
+            right,
+            center: self.locations[index],
+            tangent: direction.normalize(),
+            distance,
+            param: index as f32,
+            half_width: self.width_at(index) / 2.,
+            folded: false,
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn add_rounded_corner(&self,
-        vertices: &mut Vec<[f32; 3]>,
-        indices: &mut Vec<u32>,
-        colors: &mut Vec<[f32; 4]>,
+        sections: &mut Vec<Section>,
         index: usize,
-        location: Vec2,
-        prev: Vec2,
-        next: Vec2,
+        prev_idx: usize,
+        next_idx: usize,
+        distance: f32,
         radius: f32,
         resolution: usize
     ) {
+        let location = self.locations[index];
+        let prev = self.locations[prev_idx];
+        let next = self.locations[next_idx];
+
         let orientation = orientation_test(prev, location, next);
         if orientation == Orientation::Straight {
+            self.add_sharp_corner(sections, index, prev_idx, next_idx, distance);
             return;
         }
 
         let (side_a, side_b) = if orientation == Orientation::Right {(
-            calc_right_side_segment(prev, location, self.right_width()),
-            calc_right_side_segment(location, next, self.right_width()))
+            self.right_side(prev_idx, index),
+            self.right_side(index, next_idx))
         } else {(
-            calc_left_side_segment(prev, location, self.left_width()),
-            calc_left_side_segment(location, next, self.left_width()))
+            self.left_side(prev_idx, index),
+            self.left_side(index, next_idx))
         };
         
         let inner_angle = if orientation == Orientation::Right {
@@ -288,32 +851,60 @@ impl FlexLine {
         
         let corner_angle = 2. * PI - inner_angle;
 
-        let corner_origo = {
-            let intersection = intersection_point(
-                side_a.0, side_a.1 - side_a.0,
-                side_b.1, side_b.0 - side_b.1
-            ).unwrap();
+        let Some(intersection) = intersection_point(
+            side_a.0, side_a.1 - side_a.0,
+            side_b.1, side_b.0 - side_b.1
+        ) else {
+            // The sides are parallel, so there is no arc to follow
+            self.add_sharp_corner(sections, index, prev_idx, next_idx, distance);
+            return;
+        };
+        if !self.inner_fits(intersection, index, prev_idx, next_idx) {
+            // Too sharp a turn for the arc to fit, so pivot around the location instead
+            let steps = 1.max((resolution as f32 / (2. * PI) * (corner_angle - PI)).ceil() as usize);
+            self.add_pivot_corner(sections, index, prev_idx, next_idx, distance, steps);
+            return;
+        }
 
+        let towards_origo = {
             let angle = if orientation == Orientation::Right {
                 -inner_angle / 2.
             } else {
                 inner_angle / 2.
             };
-            let rotation_vec = Vec2::from_angle(angle);
-            let towards_origo = rotation_vec.rotate(next - location).normalize();
-            intersection + towards_origo * (radius / (corner_angle / 2.).sin())
+            Vec2::from_angle(angle).rotate(next - location).normalize()
         };
+        let origo = |radius: f32| intersection + towards_origo * (radius / (corner_angle / 2.).sin());
+        // How far before and after the location the arc starts and ends, where the center is projected onto the path
+        let extent = |radius: f32| (
+            project_point_onto_line(origo(radius), prev, location).distance(location),
+            project_point_onto_line(origo(radius), location, next).distance(location),
+        );
 
-        let out_vec = {
-            // Can't use same side, as radius=0 won't work then
-            let other_side = if orientation == Orientation::Right {
-                calc_left_side_segment(prev, location, self.left_width())
-            } else {
-                calc_right_side_segment(prev, location, self.right_width())
-            };
-            let projected = project_point_onto_line(corner_origo, other_side.0, other_side.1);
-            projected - corner_origo
+        // Keep the arc within the segments, so the sections stay in order
+        let available = (self.available_length(index, prev_idx), self.available_length(index, next_idx));
+        let (min_extent, max_extent) = (extent(0.), extent(radius));
+        let fit = |min: f32, max: f32, available: f32| if max > available && max > min {
+            ((available - min) / (max - min)).clamp(0., 1.)
+        } else {
+            1.
         };
+        let radius = radius * fit(min_extent.0, max_extent.0, available.0).min(fit(min_extent.1, max_extent.1, available.1));
+
+        let corner_origo = origo(radius);
+        let (before, after) = extent(radius);
+
+        // Perpendicular to the previous segment, pointing away from the turn.
+        // Not taken from the sides, as they lean when the width changes along the segment.
+        let direction = (location - prev).normalize();
+        let out_vec = if orientation == Orientation::Right {
+            direction.perp()
+        } else {
+            -direction.perp()
+        };
+
+        let start_distance = distance - before;
+        let end_distance = distance + after;
         
         let fan_count: i32 = 2.max((resolution as f32 / (2. * PI) * (corner_angle - PI)) as i32);
         let mut angle_step_size = (corner_angle - PI) / fan_count as f32;
@@ -322,64 +913,59 @@ impl FlexLine {
             angle_step_size = -angle_step_size;
         }
 
+        let width = self.width_at(index);
         for i in 0..fan_count + 1 {
             let angle = i as f32 * angle_step_size;
             let rotation_vec = Vec2::from_angle(angle);
-            let dir_vec = rotation_vec.rotate(out_vec).normalize();
-            let outer_vert = corner_origo + dir_vec * (radius + self.width); // left
-            let inner_vert = corner_origo + dir_vec * radius; // right
+            let dir_vec = rotation_vec.rotate(out_vec);
+            let outer_vert = corner_origo + dir_vec * (radius + width);
+            let inner_vert = corner_origo + dir_vec * radius;
 
-            if orientation == Orientation::Right {
-                vertices.push([outer_vert.x, outer_vert.y, 0.]);
-                vertices.push([inner_vert.x, inner_vert.y, 0.]);
+            let (left, right) = if orientation == Orientation::Right {
+                (outer_vert, inner_vert)
             } else {
-                vertices.push([inner_vert.x, inner_vert.y, 0.]);
-                vertices.push([outer_vert.x, outer_vert.y, 0.]);
-            }
-            colors.push(self.color.get(index, -1.));
-            colors.push(self.color.get(index,  1.));
-            
-            let start_index = vertices.len() as u32 - 4;
-            let a = start_index;
-            let b = a + 1;
-            let c = a + 2;
-            let d = a + 3;
-            self.add_quad_indices(indices, a, b, c, d);
-        }
-    }
-
-    fn add_quad_indices(&self, 
-        indices: &mut Vec<u32>,
-        a: u32, b: u32,
-        c: u32, d: u32
-    ) {
-        indices.push(a);
-        indices.push(b);
-        indices.push(c);
+                (inner_vert, outer_vert)
+            };
 
-        indices.push(b);
-        indices.push(d);
-        indices.push(c);
+            let progress = i as f32 / fan_count as f32;
+            let section_distance = start_distance + (end_distance - start_distance) * progress;
+            // The point on the path, on the segment before or after the location
+            let center = if section_distance < distance {
+                location + direction * (section_distance - distance)
+            } else {
+                location + (next - location).normalize() * (section_distance - distance)
+            };
+            sections.push(Section {
+                left,
+                mid: left.midpoint(right),
+                right,
+                center,
+                tangent: rotation_vec.rotate(direction),
+                distance: section_distance,
+                param: index as f32,
+                half_width: width / 2.,
+                folded: false,
+            });
+        }
     }
 
-    /// Add a sharp corner to the mesh, by intersecting the 2 sides, and adding a vertex at each intersection.
+    /// Add a sharp corner, by intersecting the 2 sides, and adding a section between the intersections.
     fn add_sharp_corner(&self, 
-        vertices: &mut Vec<[f32; 3]>,
-        indices: &mut Vec<u32>,
-        colors: &mut Vec<[f32; 4]>,
+        sections: &mut Vec<Section>,
         index: usize,
         prev_idx: usize,
-        next_idx: usize
+        next_idx: usize,
+        distance: f32
     ) {
         let location = self.locations[index];
         let prev = self.locations[prev_idx];
         let next = self.locations[next_idx];
 
-        let left_side_a = calc_left_side_segment(prev, location, self.left_width());
-        let left_side_b = calc_left_side_segment(location, next, self.left_width());
+        let left_side_a = self.left_side(prev_idx, index);
+        let left_side_b = self.left_side(index, next_idx);
         
-        let right_side_a = calc_right_side_segment(prev, location, self.right_width());
-        let right_side_b = calc_right_side_segment(location, next, self.right_width());
+        let right_side_a = self.right_side(prev_idx, index);
+        let right_side_b = self.right_side(index, next_idx);
         
         let left_intersection = intersection_point(
                 left_side_a.0, left_side_a.1 - left_side_a.0,
@@ -389,103 +975,589 @@ impl FlexLine {
                 right_side_a.0, right_side_a.1 - right_side_a.0,
                 right_side_b.1, right_side_b.0 - right_side_b.1);
 
-        // If the intersection is None, the corner is straight, and we don't do anything
-        if let (Some(left_vert), Some(right_vert)) = (left_intersection, right_intersection) {
-            vertices.push([left_vert.x, left_vert.y, 0.]);
-            vertices.push([right_vert.x, right_vert.y, 0.]);
-            colors.push(self.color.get(index, -1.));
-            colors.push(self.color.get(index, 1.));
-
-            let start = index * 2;
-            let prev = prev_idx as u32 * 2;
+        let direction = (location - prev).normalize() + (next - location).normalize();
+        let direction = direction.normalize_or(location - prev);
 
-            let a = prev;
-            let b = a + 1;
+        // If the intersection is None, the corner is straight, and the sides just continue
+        let (left_vert, right_vert) = match (left_intersection, right_intersection) {
+            (Some(left_vert), Some(right_vert)) => (left_vert, right_vert),
+            _ => (left_side_a.1, right_side_a.1),
+        };
 
-            let c = start as u32;
-            let d = c + 1;
-            self.add_quad_indices(indices, a, b, c, d);
+        // If the inner sides meet too far away, end the inner side of each segment at the location instead
+        match orientation_test(prev, location, next) {
+            Orientation::Left if !self.inner_fits(left_vert, index, prev_idx, next_idx) => {
+                sections.push(self.straight_section(left_side_a.1, right_vert, direction, distance, index));
+                sections.push(self.straight_section(left_side_b.0, right_vert, direction, distance, index));
+            },
+            Orientation::Right if !self.inner_fits(right_vert, index, prev_idx, next_idx) => {
+                sections.push(self.straight_section(left_vert, right_side_a.1, direction, distance, index));
+                sections.push(self.straight_section(left_vert, right_side_b.0, direction, distance, index));
+            },
+            _ => sections.push(self.straight_section(left_vert, right_vert, direction, distance, index)),
         }
     }
 
-    /// Calculate cap for unconnected path.
-    fn calc_caps(&self, vertices: &mut Vec<[f32; 3]>, indices: &mut Vec<u32>, colors: &mut Vec<[f32; 4]>) {
-        // End cap
-        let last = self.locations[self.locations.len() - 1];
-        let second_last = self.locations[self.locations.len() - 2];
-        let end_origo_left = calc_left_side_segment(second_last, last, self.left_width()).1;
-        let end_origo_right = calc_right_side_segment(second_last, last, self.right_width()).1;
-        let end_origo = end_origo_left.midpoint(end_origo_right);
-        let end_segment_vec = self.locations[self.locations.len() - 2] - self.locations[self.locations.len() - 1];
-        self.add_cap(vertices, indices, end_origo, end_segment_vec, (vertices.len() - 1) as u32, (vertices.len() - 2) as u32,
-                     self.locations.len() - 1, colors, -1.);
-        
-
-        // Start cap
-        let first = self.locations[0];
-        let second = self.locations[1];
-        let start_origo_left = calc_left_side_segment(first, second, self.left_width()).0;
-        let start_origo_right = calc_right_side_segment(first, second, self.right_width()).0;
-        let start_origo = start_origo_left.midpoint(start_origo_right);
-        let start_segment_vec = self.locations[1] - self.locations[0];
-        self.add_cap(vertices, indices, start_origo, start_segment_vec, 0, 1,
-                     0, colors, 1.);
-    }
-
-    /// Calculate cap for unconnected path.
-    fn add_cap(&self, 
-        vertices: &mut Vec<[f32; 3]>,
-        indices: &mut Vec<u32>,
-        origo: Vec2,
-        segment_vec: Vec2,
-        index_a: u32,
-        index_b: u32,
-        
+    /// Add a corner that pivots around the intersection of the inner sides,
+    /// while the outer side goes around the location in a number of steps.
+    /// A single step gives a bevel, and more steps approximate a round join.
+    fn add_pivot_corner(&self,
+        sections: &mut Vec<Section>,
         index: usize,
-        colors: &mut Vec<[f32; 4]>,
-        side_factor: f32
+        prev_idx: usize,
+        next_idx: usize,
+        distance: f32,
+        steps: usize
     ) {
-        let CornerStyle::Rounded { resolution, .. } = self.corner_style else {
+        let location = self.locations[index];
+        let prev = self.locations[prev_idx];
+        let next = self.locations[next_idx];
+
+        let orientation = if is_hairpin(prev, location, next) {
+            // Turn around the wider side, which is on the outside
+            if self.left_width(index) >= self.right_width(index) { Orientation::Right } else { Orientation::Left }
+        } else {
+            orientation_test(prev, location, next)
+        };
+        if orientation == Orientation::Straight {
+            self.add_sharp_corner(sections, index, prev_idx, next_idx, distance);
             return;
+        }
+
+        let (inner_a, inner_b, outer_width) = if orientation == Orientation::Right {(
+            self.right_side(prev_idx, index),
+            self.right_side(index, next_idx),
+            self.left_width(index))
+        } else {(
+            self.left_side(prev_idx, index),
+            self.left_side(index, next_idx),
+            self.right_width(index))
         };
-        
-        // Add origo as separate vertex
-        let origo_idx = vertices.len() as u32;
-        vertices.push([origo.x, origo.y, 0.]);
-        colors.push(self.color.get(index, 0.));
-        
-        // Add fan vertices
-        let first_vertex_idx = vertices.len() as u32;
 
-        let fan_vec = segment_vec.perp().normalize();
-        let triangles: u32 = 1.max(resolution as i32 / 2 - 2) as u32;
-        let angle_increment = PI / (triangles + 1) as f32;
-        
-        for i in 1..triangles + 1 {
-            let angle = i as f32 * angle_increment;
-            let rotation_vec = Vec2::from_angle(angle);
-            let vert = origo + (rotation_vec.rotate(fan_vec) * (self.width / 2.));
-            vertices.push([vert.x, vert.y, 0.]);
-            let gradient: f32 = ((angle - PI / 2.).sin()) * side_factor;
-            colors.push(self.color.get(index, gradient));
-        }
-
-        // First and last triangle, reuses existing vertices
-        indices.push(origo_idx);
-        indices.push(first_vertex_idx);
-        indices.push(index_a);
-
-        indices.push(origo_idx);
-        indices.push(first_vertex_idx + triangles - 1);
-        indices.push(index_b);
-
-        // Middle triangles, only using new vertices
-        if triangles > 2 {
-            for i in 0..triangles - 1 {
-                indices.push(origo_idx);
-                indices.push(first_vertex_idx + i + 1);
-                indices.push(first_vertex_idx + i);
-            }
+        // Without a usable intersection, the inner side goes straight from the end of one segment to the start of the next
+        let inner = intersection_point(
+            inner_a.0, inner_a.1 - inner_a.0,
+            inner_b.1, inner_b.0 - inner_b.1
+        ).filter(|inner| self.inner_fits(*inner, index, prev_idx, next_idx));
+
+        let direction = (location - prev).normalize();
+        // Turning straight back could give either sign
+        let turn = direction.angle_between(next - location).abs();
+        let turn = if orientation == Orientation::Right { -turn } else { turn };
+        let outer_normal = if orientation == Orientation::Right {
+            direction.perp()
+        } else {
+            -direction.perp()
+        };
+
+        for i in 0..steps + 1 {
+            let progress = i as f32 / steps as f32;
+            let rotation_vec = Vec2::from_angle(turn * progress);
+            let outer = location + rotation_vec.rotate(outer_normal) * outer_width;
+            let inner = inner.unwrap_or_else(|| inner_a.1.lerp(inner_b.0, progress));
+
+            let (left, right) = if orientation == Orientation::Right {
+                (outer, inner)
+            } else {
+                (inner, outer)
+            };
+
+            sections.push(Section {
+                left,
+                mid: left.midpoint(right),
+                right,
+                center: location,
+                tangent: rotation_vec.rotate(direction),
+                distance,
+                param: index as f32,
+                half_width: self.width_at(index) / 2.,
+                folded: false,
+            });
+        }
+    }
+
+    /// Add caps to the ends of an open strip.
+    fn add_caps(&self, strip: &mut Strip, start_cap: bool, end_cap: bool) {
+        if end_cap {
+            let end = *strip.sections.last().unwrap();
+            strip.sections.extend(cap_sections(&end, &self.end_cap));
+        }
+
+        if !start_cap {
+            return;
         }
+
+        // Start cap, made as an end cap when travelling the other way
+        let start = strip.sections[0].flipped();
+        let mut start_sections: Vec<Section> = cap_sections(&start, &self.start_cap).iter()
+            .rev()
+            .map(Section::flipped)
+            .collect();
+        start_sections.append(&mut strip.sections);
+        strip.sections = start_sections;
+    }
+}
+
+/// Whether the line turns straight back at `location`
+fn is_hairpin(prev: Vec2, location: Vec2, next: Vec2) -> bool {
+    orientation_test(prev, location, next) == Orientation::Straight && (location - prev).dot(next - location) < 0.
+}
+
+/// Calculate the sections of a cap after the given section.
+fn cap_sections(end: &Section, cap: &CapStyle) -> Vec<Section> {
+    let forward = end.tangent * end.half_width;
+    let left = end.tangent.perp() * end.half_width;
+    let outline = cap.outline().into_iter().map(|p| end.mid + forward * p.x + left * p.y).collect();
+    fan_sections(end, end.mid, outline)
+}
+
+/// Make a strip with the marker, placed at the end of the given section.
+fn marker_strip(end: &Section, marker: &Marker, width: f32) -> Strip {
+    let (outline, origo) = marker.outline(width);
+    let to_world = |p: Vec2| end.mid + end.tangent * p.x + end.tangent.perp() * p.y;
+    Strip {
+        sections: fan_sections(end, to_world(origo), outline.into_iter().map(to_world).collect()),
+        closed: false,
     }
-}
\ No newline at end of file
+}
+
+/// Calculate the sections of a fan from `origo` to the outline, which goes from the left side to the right side.
+/// The sections fold around `origo`, narrowing down to the tip of the outline.
+/// Other properties are copied from `template`.
+fn fan_sections(template: &Section, origo: Vec2, mut outline: Vec<Vec2>) -> Vec<Section> {
+    if outline.len() < 2 {
+        return Vec::new();
+    }
+    if outline.len() % 2 == 0 {
+        // Make sure there is a vertex at the tip, where the two sides meet
+        let tip = outline[outline.len() / 2 - 1].midpoint(outline[outline.len() / 2]);
+        outline.insert(outline.len() / 2, tip);
+    }
+
+    let tip = outline.len() / 2;
+    (0..tip + 1).map(|i| Section {
+        left: outline[i],
+        mid: origo,
+        right: outline[outline.len() - 1 - i],
+        folded: true,
+        ..*template
+    }).collect()
+}
+
+#[cfg(test)]
+use crate::mesh_tests::MeshParts;
+
+#[test]
+fn test_dashed_line() {
+    let mut line = FlexLine::new(
+        vec![Vec2::new(0., 0.), Vec2::new(10., 0.)],
+        2.,
+        Alignment::Center,
+        CornerStyle::Sharp,
+        ConnectionStyle::Unconnected,
+        LineColor::Fill(Color::WHITE),
+    );
+    line.dash_pattern = Some(DashPattern::dashed(4., 2.));
+
+    let positions = MeshParts::new(&line.make_mesh()).positions;
+    let xs: Vec<f32> = positions.iter().step_by(3).map(|p| p.x).collect();
+    assert_eq!(xs, vec![0., 4., 6., 10.]);
+}
+
+#[test]
+fn test_dash_cuts() {
+    let mut line = FlexLine::new(
+        vec![Vec2::new(0., 0.), Vec2::new(10., 0.), Vec2::new(10., 10.)],
+        2.,
+        Alignment::Center,
+        CornerStyle::Bevel,
+        ConnectionStyle::Unconnected,
+        LineColor::Fill(Color::WHITE),
+    );
+    line.dash_pattern = Some(DashPattern::dashed(5., 5.));
+    let positions = MeshParts::new(&line.make_mesh()).positions;
+
+    // Dashes are cut straight across the segment, also next to the corner
+    let cut = |left: Vec2, right: Vec2| positions.contains(&left) && positions.contains(&right) && positions.contains(&left.midpoint(right));
+    assert!(cut(Vec2::new(5., 1.), Vec2::new(5., -1.)));
+    // The second dash starts at the corner, on the next segment
+    assert!(cut(Vec2::new(9., 0.), Vec2::new(11., 0.)));
+    assert!(cut(Vec2::new(9., 5.), Vec2::new(11., 5.)));
+}
+
+#[test]
+fn test_miter_limit() {
+    // A spike that turns by 160°, giving a miter length of about 5.8 times the width
+    let locations = vec![Vec2::new(0., 0.), Vec2::new(10., 0.), Vec2::new(0., 3.5)];
+    let max_x = |corner_style| {
+        let line = FlexLine::new(locations.clone(), 1., Alignment::Center, corner_style, ConnectionStyle::Unconnected, LineColor::Fill(Color::WHITE));
+        MeshParts::new(&line.make_mesh()).positions.iter().map(|p| p.x).fold(f32::MIN, f32::max)
+    };
+
+    assert!(max_x(CornerStyle::Sharp) > 12.);
+    assert!(max_x(CornerStyle::Miter { limit: 10. }) > 12.);
+    assert!(max_x(CornerStyle::Miter { limit: 4. }) < 10.5);
+    assert!(max_x(CornerStyle::Bevel) < 10.5);
+    assert!(max_x(CornerStyle::Round { resolution: 32 }) <= 10.5 + 1e-4);
+}
+
+#[test]
+fn test_cap_styles() {
+    let extent = |cap: CapStyle| {
+        let mut line = FlexLine::new(vec![Vec2::new(0., 0.), Vec2::new(10., 0.)], 2., Alignment::Center, CornerStyle::Sharp, ConnectionStyle::Unconnected, LineColor::Fill(Color::WHITE));
+        line.start_cap = cap.clone();
+        line.end_cap = cap;
+        let positions = MeshParts::new(&line.make_mesh()).positions;
+        let min = positions.iter().map(|p| p.x).fold(f32::MAX, f32::min);
+        let max = positions.iter().map(|p| p.x).fold(f32::MIN, f32::max);
+        (min, max)
+    };
+
+    assert_eq!(extent(CapStyle::Butt), (0., 10.));
+    assert_eq!(extent(CapStyle::Square), (-1., 11.));
+    assert_eq!(extent(CapStyle::Triangle), (-1., 11.));
+    let (min, max) = extent(CapStyle::Round { resolution: 16 });
+    assert!((min + 1.).abs() < 1e-5 && (max - 11.).abs() < 1e-5);
+    assert_eq!(extent(CapStyle::Custom(vec![Vec2::new(0., 1.), Vec2::new(3., 0.), Vec2::new(0., -1.)])), (-3., 13.));
+}
+
+#[test]
+fn test_markers() {
+    let mut line = FlexLine::new(vec![Vec2::new(0., 0.), Vec2::new(10., 0.)], 1., Alignment::Center, CornerStyle::Sharp, ConnectionStyle::Unconnected, LineColor::Fill(Color::WHITE));
+    line.end_cap = CapStyle::Round { resolution: 16 };
+    line.end_marker = Some(Marker::new(crate::MarkerShape::Arrow, 3.));
+
+    let positions = MeshParts::new(&line.make_mesh()).positions;
+    // The tip of the arrow is at the end of the line, and nothing pokes through it
+    let max_x = positions.iter().map(|p| p.x).fold(f32::MIN, f32::max);
+    assert_eq!(max_x, 10.);
+    // The stroke stops at the base of the arrow
+    assert!(positions.contains(&Vec2::new(7., 0.5)));
+    assert!(!positions.iter().any(|p| p.x > 7. && p.y == 0.5));
+}
+
+#[test]
+fn test_tapered_line() {
+    let mut line = FlexLine::new(vec![Vec2::new(0., 0.), Vec2::new(10., 0.), Vec2::new(20., 0.)], 1., Alignment::Center, CornerStyle::Sharp, ConnectionStyle::Unconnected, LineColor::Fill(Color::WHITE));
+    line.widths = Some(vec![0., 2., 4.]);
+
+    let positions = MeshParts::new(&line.make_mesh()).positions;
+    let left_ys: Vec<f32> = positions.iter().step_by(3).map(|p| p.y).collect();
+    assert_eq!(left_ys, vec![0., 1., 2.]);
+}
+
+#[test]
+fn test_width_profile() {
+    let mut line = FlexLine::new(vec![Vec2::new(0., 0.), Vec2::new(10., 0.)], 2., Alignment::Center, CornerStyle::Sharp, ConnectionStyle::Unconnected, LineColor::Fill(Color::WHITE));
+    line.width_profile = Some(WidthProfile::keys(vec![(0., 0.), (0.5, 1.), (1., 0.)]));
+
+    let positions = MeshParts::new(&line.make_mesh()).positions;
+    let left: Vec<Vec2> = positions.iter().step_by(3).copied().collect();
+    assert_eq!(left, vec![Vec2::new(0., 0.), Vec2::new(5., 1.), Vec2::new(10., 0.)]);
+}
+
+#[test]
+fn test_uvs() {
+    let mut line = FlexLine::new(vec![Vec2::new(0., 0.), Vec2::new(10., 0.), Vec2::new(10., 10.)], 2., Alignment::Center, CornerStyle::Sharp, ConnectionStyle::Unconnected, LineColor::Fill(Color::WHITE));
+    line.end_cap = CapStyle::Square;
+    line.uv_mode = UvMode::Repeat { length: 4. };
+
+    let mesh = line.make_mesh();
+    let uvs = match mesh.attribute(Mesh::ATTRIBUTE_UV_0).unwrap() {
+        bevy::render::mesh::VertexAttributeValues::Float32x2(uvs) => uvs.clone(),
+        _ => panic!("Expected Float32x2 UVs"),
+    };
+    assert_eq!(&uvs[0..3], &[[0., 0.], [0., 0.5], [0., 1.]]);
+    assert_eq!(&uvs[3..6], &[[2.5, 0.], [2.5, 0.5], [2.5, 1.]]);
+    assert_eq!(&uvs[6..9], &[[5., 0.], [5., 0.5], [5., 1.]]);
+    // The square cap continues half the width beyond the end
+    assert_eq!(uvs.iter().map(|uv| uv[0]).fold(f32::MIN, f32::max), 5.25);
+
+    // Repeating over the widths, as the width is not used with them
+    line.width = 0.;
+    line.widths = Some(vec![2., 2., 2.]);
+    line.uv_mode = UvMode::Normalized;
+    let mesh = line.make_mesh();
+    let Some(bevy::render::mesh::VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0) else {
+        panic!("Expected Float32x2 UVs");
+    };
+    assert_eq!(&uvs[6..9], &[[10., 0.], [10., 0.5], [10., 1.]]);
+}
+
+#[test]
+fn test_merge_overlaps() {
+    // Two strokes crossing at a right angle, overlapping in a 2 by 2 square
+    let locations = vec![Vec2::new(0., 0.), Vec2::new(10., 10.), Vec2::new(10., 0.), Vec2::new(0., 10.)];
+    let mut line = FlexLine::new(locations, 2., Alignment::Center, CornerStyle::Bevel, ConnectionStyle::Unconnected, LineColor::Fill(Color::WHITE));
+    let area = |line: &FlexLine| MeshParts::new(&line.make_mesh()).area();
+
+    let overlapping = area(&line);
+    line.merge_overlaps = true;
+    assert!((overlapping - area(&line) - 4.).abs() < 1e-3);
+}
+
+#[test]
+fn test_validate() {
+    let locations = vec![Vec2::new(0., 0.), Vec2::new(10., 0.)];
+    let try_new = |locations: Vec<Vec2>, width: f32, color: LineColor| {
+        FlexLine::try_new(locations, width, Alignment::Center, CornerStyle::Sharp, ConnectionStyle::Unconnected, color).err()
+    };
+    let white = LineColor::Fill(Color::WHITE);
+
+    assert_eq!(try_new(locations.clone(), 1., white.clone()), None);
+    assert_eq!(try_new(vec![Vec2::ZERO], 1., white.clone()), Some(FlexLineError::TooFewLocations(1)));
+    assert_eq!(try_new(vec![Vec2::ZERO, Vec2::NAN], 1., white.clone()), Some(FlexLineError::InvalidLocation(1)));
+    assert_eq!(try_new(locations.clone(), -1., white.clone()), Some(FlexLineError::InvalidWidth(-1.)));
+    assert_eq!(
+        try_new(locations.clone(), 1., LineColor::PerVertex(vec![Color::WHITE])),
+        Some(FlexLineError::ColorCount { expected: 2, found: 1 }),
+    );
+
+    let mut line = FlexLine::new(locations, 1., Alignment::Center, CornerStyle::Sharp, ConnectionStyle::Unconnected, white);
+    line.widths = Some(vec![1.]);
+    assert_eq!(line.validate(), Err(FlexLineError::WidthCount { expected: 2, found: 1 }));
+
+    line.widths = None;
+    line.dash_pattern = Some(DashPattern::dashed(-1., 1.));
+    assert_eq!(line.validate(), Err(FlexLineError::InvalidDashLength(-1.)));
+    line.dash_pattern = Some(DashPattern::dotted(0.));
+    assert_eq!(line.validate(), Err(FlexLineError::EmptyDashPattern));
+    line.dash_pattern = Some(DashPattern::new(vec![1., 1.], f32::NAN));
+    assert!(matches!(line.validate(), Err(FlexLineError::InvalidDashOffset(_))));
+
+    line.dash_pattern = None;
+    line.uv_mode = UvMode::Repeat { length: 0. };
+    assert_eq!(line.validate(), Err(FlexLineError::InvalidUvLength(0.)));
+    line.uv_mode = UvMode::Normalized;
+    line.width = 0.;
+    assert_eq!(line.validate(), Err(FlexLineError::InvalidUvLength(0.)));
+    // The widths are used instead of the width
+    line.widths = Some(vec![2., 2.]);
+    assert_eq!(line.validate(), Ok(()));
+
+    line.end_cap = CapStyle::Custom(vec![Vec2::new(0., 1.), Vec2::NAN, Vec2::new(0., -1.)]);
+    assert_eq!(line.validate(), Err(FlexLineError::InvalidCapStyle));
+    line.end_cap = CapStyle::Butt;
+    line.end_marker = Some(Marker::new(crate::MarkerShape::Arrow, f32::NAN));
+    assert!(matches!(line.validate(), Err(FlexLineError::InvalidMarkerScale(_))));
+    line.end_marker = None;
+    line.width_profile = Some(WidthProfile::keys(vec![(f32::NAN, 1.)]));
+    assert_eq!(line.validate(), Err(FlexLineError::InvalidWidthProfile));
+    line.width_profile = Some(WidthProfile::keys(vec![(0.5, -1.)]));
+    assert_eq!(line.validate(), Err(FlexLineError::InvalidWidthProfile));
+    line.width_profile = None;
+    line.smoothing = Some(Smoothing::CatmullRom { tolerance: f32::NAN });
+    assert!(matches!(line.validate(), Err(FlexLineError::InvalidSmoothing(_))));
+
+    line.smoothing = None;
+    line.corner_style = CornerStyle::Miter { limit: 0.5 };
+    assert_eq!(line.validate(), Err(FlexLineError::InvalidCornerStyle));
+    line.corner_style = CornerStyle::Miter { limit: 1. };
+    assert_eq!(line.validate(), Ok(()));
+}
+
+#[test]
+fn test_degenerate_corners() {
+    let cases = [
+        // Repeated locations
+        vec![Vec2::new(0., 0.), Vec2::new(0., 0.), Vec2::new(10., 0.), Vec2::new(10., 0.), Vec2::new(10., 10.)],
+        // Collinear locations
+        vec![Vec2::new(0., 0.), Vec2::new(5., 0.), Vec2::new(10., 0.), Vec2::new(10., 10.)],
+        // Turning straight back, and nearly straight back
+        vec![Vec2::new(0., 0.), Vec2::new(10., 0.), Vec2::new(5., 0.), Vec2::new(5., 10.)],
+        vec![Vec2::new(0., 0.), Vec2::new(10., 0.), Vec2::new(0., 0.01), Vec2::new(3., 5.)],
+    ];
+    let corner_styles = [
+        CornerStyle::Rounded { radius: 2., resolution: 16 },
+        CornerStyle::Miter { limit: 4. },
+        CornerStyle::Bevel,
+        CornerStyle::Round { resolution: 16 },
+    ];
+
+    // Offset past the path, the inner sides of a turn meet beyond the location
+    let alignments = [Alignment::Center, Alignment::Offset(1.)];
+
+    for locations in cases {
+        for (corner_style, alignment) in corner_styles.into_iter().flat_map(|style| alignments.map(|alignment| (style, alignment))) {
+            let line = FlexLine::new(locations.clone(), 1., alignment, corner_style, ConnectionStyle::Unconnected, LineColor::Fill(Color::WHITE));
+            let parts = MeshParts::new(&line.make_mesh());
+            assert!(parts.positions.iter().all(|p| p.is_finite()));
+
+            // No spikes where the sides meet far away, so the area stays close to the length times the width
+            let area = parts.area();
+            let length: f32 = locations.windows(2).map(|pair| pair[0].distance(pair[1])).sum();
+            assert!(area > 0.5 * length && area < 1.5 * length);
+        }
+    }
+}
+
+#[test]
+fn test_feathering() {
+    let mut line = FlexLine::new(
+        vec![Vec2::new(0., 0.), Vec2::new(10., 0.)],
+        2.,
+        Alignment::Center,
+        CornerStyle::Sharp,
+        ConnectionStyle::Unconnected,
+        LineColor::GradientAcross { left: Color::srgb(1., 0., 0.), right: Color::srgb(0., 0., 1.) },
+    );
+    line.feathering_width = Some(0.5);
+    let MeshParts { positions, colors, line_positions, .. } = MeshParts::new(&line.make_mesh());
+
+    // A fringe on each side, and a faded section beyond each butt end
+    assert_eq!(positions.len(), 4 * 5);
+    let min = positions.iter().fold(Vec2::MAX, |min, p| min.min(*p));
+    let max = positions.iter().fold(Vec2::MIN, |max, p| max.max(*p));
+    assert_eq!((min, max), (Vec2::new(-0.5, -1.5), Vec2::new(10.5, 1.5)));
+
+    // Only the vertices on the line itself are opaque, and the fringe keeps the color of the side
+    for (position, color) in positions.iter().zip(&colors) {
+        let inside = position.x >= 0. && position.x <= 10. && position.y.abs() <= 1.;
+        assert_eq!(color[3], if inside { 1. } else { 0. });
+    }
+    assert_eq!(&colors[5][..3], &[1., 0., 0.]);
+
+    // The shader smooths the pixel inside -1 and 1 across, which is at the outer edge of the fringe, not at the sides of the line
+    for ((position, [_, across]), color) in positions.iter().zip(&line_positions).zip(&colors) {
+        if position.y.abs() == 1.5 {
+            assert_eq!(across.abs(), 1.);
+        } else if color[3] == 1. {
+            assert!((across.abs() - position.y.abs() / 1.5).abs() < 1e-6);
+        }
+    }
+}
+
+#[test]
+fn test_line_positions() {
+    let mut line = FlexLine::new(
+        vec![Vec2::new(0., 0.), Vec2::new(10., 0.)],
+        2.,
+        Alignment::Center,
+        CornerStyle::Sharp,
+        ConnectionStyle::Unconnected,
+        LineColor::Fill(Color::WHITE),
+    );
+    line.end_cap = CapStyle::Round { resolution: 8 };
+    let line_positions = MeshParts::new(&line.make_mesh()).line_positions;
+
+    assert_eq!(&line_positions[..6], &[[0., -1.], [0., 0.], [0., 1.], [10., -1.], [10., 0.], [10., 1.]]);
+    // The cap folds around the end, so its outline is at the sides, and further along the line
+    let cap = &line_positions[6..];
+    assert!(cap.iter().all(|[_, across]| across.abs() == 1. || *across == 0.));
+    assert!((cap.iter().map(|[distance, _]| *distance).fold(0., f32::max) - 11.).abs() < 1e-5);
+}
+
+#[test]
+fn test_border() {
+    let mut line = FlexLine::new(
+        vec![Vec2::new(0., 0.), Vec2::new(10., 0.)],
+        4.,
+        Alignment::Center,
+        CornerStyle::Sharp,
+        ConnectionStyle::Unconnected,
+        LineColor::Fill(Color::WHITE),
+    );
+    let extent = |line: &FlexLine| MeshParts::new(&line.make_mesh()).positions.iter().map(|p| p.y).fold(0., f32::max);
+
+    line.border = Some(Border::new(1., Color::BLACK, crate::BorderPlacement::Inside));
+    assert_eq!(extent(&line), 2.);
+    line.border = Some(Border::new(1., Color::BLACK, crate::BorderPlacement::Both));
+    assert_eq!(extent(&line), 2.5);
+    line.border = Some(Border::new(1., Color::BLACK, crate::BorderPlacement::Outside));
+    assert_eq!(extent(&line), 3.);
+
+    // Each section has a band on each side, and the line between them, with separate vertices where the color changes
+    let MeshParts { positions, colors, indices, .. } = MeshParts::new(&line.make_mesh());
+    let across: Vec<f32> = positions[..7].iter().map(|p| p.y).collect();
+    for (found, expected) in across.iter().zip([3., 2., 2., 0., -2., -2., -3.]) {
+        assert!((found - expected).abs() < 1e-5);
+    }
+    let brightness: Vec<f32> = colors[..7].iter().map(|c| c[0].round()).collect();
+    assert_eq!(brightness, vec![0., 0., 1., 1., 1., 0., 0.]);
+    assert_eq!(indices.len(), 3 * 2 * 4);
+}
+
+#[test]
+fn test_bands() {
+    let grey = Color::srgb(0.5, 0.5, 0.5);
+    let mut line = FlexLine::new(
+        vec![Vec2::new(0., 0.), Vec2::new(10., 0.)],
+        10.,
+        Alignment::Center,
+        CornerStyle::Sharp,
+        ConnectionStyle::Unconnected,
+        LineColor::Fill(grey),
+    );
+    // A road with shoulders, and a dashed stripe in the middle
+    line.bands = vec![
+        Band::new(0.1, Color::BLACK),
+        Band { fraction: 0.35, color: None, dash_pattern: None },
+        Band::dashed(0.1, Color::WHITE, DashPattern::dashed(2., 2.)),
+        Band { fraction: 0.35, color: None, dash_pattern: None },
+        Band::new(0.1, Color::BLACK),
+    ];
+    let MeshParts { positions, colors, .. } = MeshParts::new(&line.make_mesh());
+
+    // Each band has its own vertices at its edges, and the stripe has one in the middle.
+    // The 3 dashes are added on top, with the line in the gaps.
+    assert_eq!(positions.len(), 2 * 11 + 3 * 2 * 3);
+    let across: Vec<f32> = positions[..11].iter().map(|p| (p.y * 10.).round() / 10.).collect();
+    assert_eq!(across, vec![5., 4., 4., 0.5, 0.5, 0., -0.5, -0.5, -4., -4., -5.]);
+    let brightness: Vec<f32> = colors[..11].iter().map(|c| (c[0] * 10.).round() / 10.).collect();
+    assert_eq!(brightness, vec![0., 0., 0.5, 0.5, 0.5, 0.5, 0.5, 0.5, 0.5, 0., 0.]);
+    assert!(colors[22..].iter().all(|c| c[0] > 0.99));
+    assert_eq!(positions[22].x, 0.);
+    assert_eq!(positions[positions.len() - 1].x, 10.);
+}
+
+#[test]
+fn test_fill() {
+    let square = vec![Vec2::new(0., 0.), Vec2::new(10., 0.), Vec2::new(10., 10.), Vec2::new(0., 10.)];
+    let mut line = FlexLine::new(square, 1., Alignment::Center, CornerStyle::Sharp, ConnectionStyle::Connected, LineColor::Fill(Color::WHITE));
+    line.fill = Some(Fill::new(Color::BLACK));
+
+    let mut parts = MeshParts::new(&line.make_mesh());
+
+    // The fill comes first, so the line is drawn over it
+    assert!(parts.line_positions[..4].iter().all(|p| p[1] == 2.));
+    assert!(parts.line_positions[4..].iter().all(|p| p[1].abs() <= 1.));
+    parts.indices.truncate(6);
+    assert_eq!(parts.area(), 100.);
+
+    // Open lines have no inside
+    line.connection_style = ConnectionStyle::Unconnected;
+    let line_positions = MeshParts::new(&line.make_mesh()).line_positions;
+    assert!(line_positions.iter().all(|p| p[1] != 2.));
+}
+
+#[test]
+fn test_gradient_along() {
+    let gradient = Gradient::new(vec![(0., Color::BLACK), (0.5, Color::WHITE), (1., Color::BLACK)], crate::Spread::Clamp);
+    let color = LineColor::GradientAlong { gradient, units: StopUnits::Normalized };
+    let mut line = FlexLine::new(vec![Vec2::new(0., 0.), Vec2::new(10., 0.)], 2., Alignment::Center, CornerStyle::Sharp, ConnectionStyle::Unconnected, color);
+    line.end_cap = CapStyle::Square;
+    let MeshParts { positions, colors, .. } = MeshParts::new(&line.make_mesh());
+
+    // A row of vertices is added at the stop in the middle
+    let brightness = |x: f32| positions.iter().zip(&colors).filter(|(p, _)| (p.x - x).abs() < 1e-4).map(|(_, c)| (c[0] * 100.).round() / 100.).collect::<Vec<_>>();
+    assert_eq!(brightness(5.), vec![1.; 3]);
+    assert_eq!(brightness(2.5), Vec::<f32>::new());
+    // The cap beyond the end keeps the color of the last stop
+    assert!(brightness(10.).iter().chain(&brightness(11.)).all(|c| *c == 0.));
+}
+
+#[test]
+fn test_gradient_across_stops() {
+    // A hot core, off the middle
+    let gradient = Gradient::new(vec![(0., Color::BLACK), (0.25, Color::WHITE), (1., Color::BLACK)], crate::Spread::Clamp);
+    let line = FlexLine::new(vec![Vec2::new(0., 0.), Vec2::new(10., 0.)], 2., Alignment::Center, CornerStyle::Sharp, ConnectionStyle::Unconnected, LineColor::GradientAcrossStops(gradient));
+
+    let MeshParts { positions, colors, .. } = MeshParts::new(&line.make_mesh());
+
+    // Each row has a vertex at the stop, besides the sides and the middle
+    assert_eq!(positions.len(), 2 * 4);
+    let across: Vec<f32> = positions[..4].iter().map(|p| p.y).collect();
+    assert_eq!(across, vec![1., 0.5, 0., -1.]);
+    let brightness: Vec<f32> = colors[..4].iter().map(|c| (c[0] * 100.).round() / 100.).collect();
+    assert_eq!(brightness, vec![0., 1., 0.67, 0.]);
+}
@@ -0,0 +1,102 @@
+#import bevy_sprite::{
+    mesh2d_functions as mesh_functions,
+    mesh2d_view_bindings::view,
+}
+
+#ifdef TONEMAP_IN_SHADER
+#import bevy_core_pipeline::tonemapping
+#endif
+
+struct FlexLineMaterial {
+    color: vec4<f32>,
+    // Added to the UVs before sampling the texture, to scroll it
+    uv_offset: vec2<f32>,
+    // The part of the width that is drawn
+    thickness: f32,
+    dash_offset: f32,
+    // Lengths of the dashes and the gaps between them. No dashes while the gap is 0
+    dashes: vec2<f32>,
+    glow: vec4<f32>,
+};
+
+@group(2) @binding(0) var<uniform> material: FlexLineMaterial;
+@group(2) @binding(1) var texture: texture_2d<f32>;
+@group(2) @binding(2) var texture_sampler: sampler;
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) position: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) color: vec4<f32>,
+    // Distance along the line, and position across it from -1 on the left side to 1 on the right side.
+    // Feathered lines have their fringe at the ends of that range. Fills are at 2 across
+    @location(3) line_position: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) line_position: vec2<f32>,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    let world_from_local = mesh_functions::get_world_from_local(vertex.instance_index);
+    out.position = mesh_functions::mesh2d_position_local_to_clip(world_from_local, vec4<f32>(vertex.position, 1.0));
+    out.uv = vertex.uv;
+    out.color = vertex.color;
+    out.line_position = vertex.line_position;
+    return out;
+}
+
+// How much of the pixel is below the edge, given how much the value changes over a pixel.
+// The ramp is over the pixel inside the edge, as the mesh has no geometry beyond the sides of the line
+fn coverage(value: f32, edge: f32, pixel: f32) -> f32 {
+    return clamp((edge - value) / max(pixel, 1e-5), 0.0, 1.0);
+}
+
+@fragment
+fn fragment(
+    mesh: VertexOutput,
+) -> @location(0) vec4<f32> {
+    // Without a texture, this samples a white fallback image
+    var line = material.color * mesh.color * textureSample(texture, texture_sampler, mesh.uv + material.uv_offset);
+
+    // Derivatives are taken outside the branch, as they need uniform control flow
+    let across = abs(mesh.line_position.y);
+    let across_pixel = fwidth(across);
+    let along_pixel = fwidth(mesh.line_position.x);
+
+    // Fills are at 2 across, and have no edges, glow or dashes
+    var glow = vec4<f32>(0.0);
+    if across < 1.5 {
+        // Smooth edges, from how far the pixel is from the middle of the line
+        line.a *= coverage(across, material.thickness, across_pixel);
+
+        // The glow is under the line, and fades out linearly from the drawn part to the sides
+        glow = material.glow;
+        let fade = clamp((across - material.thickness) / max(1.0 - material.thickness, 1e-5), 0.0, 1.0);
+        glow.a *= (1.0 - fade) * coverage(across, 1.0, across_pixel);
+
+        if material.dashes.y > 0.0 {
+            let period = material.dashes.x + material.dashes.y;
+            let along = mesh.line_position.x + material.dash_offset;
+            let in_period = along - period * floor(along / period);
+            // Fade in at the start of the dash, as well as out at the end
+            let dash = min(coverage(-in_period, 0.0, along_pixel), coverage(in_period, material.dashes.x, along_pixel));
+            line.a *= dash;
+            glow.a *= dash;
+        }
+    }
+
+    // The line is drawn over the glow
+    let alpha = line.a + glow.a * (1.0 - line.a);
+    let rgb = (line.rgb * line.a + glow.rgb * glow.a * (1.0 - line.a)) / max(alpha, 1e-5);
+    var output_color = vec4<f32>(rgb, alpha);
+#ifdef TONEMAP_IN_SHADER
+    output_color = tonemapping::tone_mapping(output_color, view.color_grading);
+#endif
+    return output_color;
+}
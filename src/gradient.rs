@@ -0,0 +1,134 @@
+use bevy::color::{Color, Mix, Srgba};
+
+/// Most repeats of a gradient over a strip that get vertices at their stops.
+/// Beyond that, the repeats are too small to see, and the colors are only taken at the vertices the strip already has.
+const MAX_REPEATS: f32 = 1000.;
+
+/// Colors at positions, blended between them
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    /// Positions and their colors, in increasing order
+    pub stops: Vec<(f32, Color)>,
+    pub spread: Spread,
+}
+
+/// How a gradient continues before its first stop and after its last
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Spread {
+    /// The colors of the first and last stop continue
+    Clamp,
+    /// The stops start over after the last one
+    Repeat,
+    /// The stops go back and forth
+    Mirror,
+}
+
+/// What the positions of the stops of a gradient along a line are measured in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopUnits {
+    /// Fractions of the length of the line, from 0 at the start to 1 at the end
+    Normalized,
+    /// World units from the start of the line
+    Distance,
+}
+
+impl StopUnits {
+    /// Position in the gradient at the distance along a line with the given length
+    pub(crate) fn position(&self, distance: f32, length: f32) -> f32 {
+        match self {
+            StopUnits::Normalized => distance / length.max(f32::EPSILON),
+            StopUnits::Distance => distance,
+        }
+    }
+
+    pub(crate) fn distance(&self, position: f32, length: f32) -> f32 {
+        match self {
+            StopUnits::Normalized => position * length.max(f32::EPSILON),
+            StopUnits::Distance => position,
+        }
+    }
+}
+
+impl Gradient {
+    pub fn new(stops: Vec<(f32, Color)>, spread: Spread) -> Self {
+        Gradient { stops, spread }
+    }
+
+    /// Whether the positions are finite and in order
+    pub(crate) fn is_valid(&self) -> bool {
+        self.stops.iter().all(|(position, _)| position.is_finite())
+            && self.stops.windows(2).all(|pair| pair[0].0 <= pair[1].0)
+    }
+
+    /// Span between the first and the last stop
+    fn span(&self) -> Option<(f32, f32)> {
+        let (first, last) = (self.stops.first()?.0, self.stops.last()?.0);
+        (last > first).then_some((first, last - first))
+    }
+
+    pub(crate) fn color_at(&self, position: f32) -> Srgba {
+        let position = match (self.spread, self.span()) {
+            (Spread::Repeat, Some((first, span))) => first + (position - first).rem_euclid(span),
+            (Spread::Mirror, Some((first, span))) => {
+                let t = (position - first).rem_euclid(2. * span);
+                first + if t > span { 2. * span - t } else { t }
+            },
+            _ => position,
+        };
+
+        let next = self.stops.partition_point(|(stop, _)| *stop <= position);
+        match (self.stops.get(next.wrapping_sub(1)), self.stops.get(next)) {
+            (Some((from, a)), Some((to, b))) => a.to_srgba().mix(&b.to_srgba(), (position - from) / (to - from)),
+            (Some((_, color)), None) | (None, Some((_, color))) => color.to_srgba(),
+            (None, None) => Srgba::WHITE,
+        }
+    }
+
+    /// Positions of the stops between `from` and `to`, which need vertices for the colors to blend right.
+    /// Repeating gradients have their stops repeated, unless they repeat more than [`MAX_REPEATS`] times, and then there are none.
+    pub(crate) fn stops_between(&self, from: f32, to: f32) -> Vec<f32> {
+        let between = |position: &f32| *position > from && *position < to;
+        let Some((first, span)) = self.span().filter(|_| self.spread != Spread::Clamp) else {
+            return self.stops.iter().map(|(position, _)| *position).filter(between).collect();
+        };
+        if (to - from) / span > MAX_REPEATS {
+            return Vec::new();
+        }
+
+        let mut positions = Vec::new();
+        let start = ((from - first) / span).floor() as i64;
+        let end = ((to - first) / span).ceil() as i64;
+        for repeat in start..end {
+            let offset = first + repeat as f32 * span;
+            let mirrored = self.spread == Spread::Mirror && repeat.rem_euclid(2) == 1;
+            let stops = self.stops.iter().map(|(position, _)| position - first);
+            if mirrored {
+                positions.extend(stops.rev().map(|position| offset + span - position));
+            } else {
+                positions.extend(stops.map(|position| offset + position));
+            }
+        }
+        positions.retain(between);
+        positions.dedup();
+        positions
+    }
+}
+
+#[test]
+fn test_spread() {
+    let stops = vec![(1., Color::BLACK), (3., Color::WHITE)];
+    let brightness = |spread: Spread, position: f32| (Gradient::new(stops.clone(), spread).color_at(position).red * 100.).round() / 100.;
+    assert_eq!(brightness(Spread::Clamp, 0.), 0.);
+    assert_eq!(brightness(Spread::Clamp, 2.), 0.5);
+    assert_eq!(brightness(Spread::Clamp, 5.), 1.);
+    assert_eq!(brightness(Spread::Repeat, 4.), 0.5);
+    assert_eq!(brightness(Spread::Repeat, 0.), 0.5);
+    assert_eq!(brightness(Spread::Mirror, 4.), 0.5);
+    assert_eq!(brightness(Spread::Mirror, 3.5), 0.75);
+    assert_eq!(brightness(Spread::Mirror, 0.), 0.5);
+
+    let gradient = Gradient::new(stops, Spread::Mirror);
+    assert_eq!(gradient.stops_between(0., 8.), vec![1., 3., 5., 7.]);
+    assert_eq!(Gradient { spread: Spread::Clamp, ..gradient.clone() }.stops_between(0., 8.), vec![1., 3.]);
+    assert!(gradient.stops_between(0., 2. * (MAX_REPEATS + 1.)).is_empty());
+}
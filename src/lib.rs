@@ -3,17 +3,48 @@ mod bundle;
 
 //#[allow(dead_code)]
 mod flex_line;
+mod error;
+mod border;
+mod band;
+mod fill;
+mod gradient;
 mod vector_utils;
+mod stroke;
+mod dash;
+mod marker;
+mod profile;
+mod material;
+mod path;
+mod spline;
+mod svg;
+mod svg_export;
+mod sweep;
+mod offset;
+#[cfg(test)]
+mod mesh_tests;
 
-pub(crate) const BASE_MATERIAL_HANDLE: Handle<ColorMaterial> = Handle::weak_from_u128(0xf724befa6c0e7f11d40d8931715303ac);
+pub(crate) const BASE_MATERIAL_HANDLE: Handle<FlexLineMaterial> = Handle::weak_from_u128(0xf724befa6c0e7f11d40d8931715303ac);
 
-use bevy::{asset::Handle, sprite::ColorMaterial};
+use bevy::asset::Handle;
 
 pub use crate::{
     plugin::FlexLine2dPlugin, 
     bundle::FlexLine2dBundle,
     flex_line::{
-        FlexLine, CornerStyle, Alignment, 
-        ConnectionStyle, LineColor
+        FlexLine, CornerStyle, CapStyle, Alignment, 
+        ConnectionStyle, LineColor, UvMode
     },
+    error::FlexLineError,
+    border::{Border, BorderPlacement},
+    band::Band,
+    fill::{Fill, FillColor, FillRule},
+    gradient::{Gradient, Spread, StopUnits},
+    dash::DashPattern,
+    marker::{Marker, MarkerShape},
+    profile::WidthProfile,
+    material::{FlexLineMaterial, TextureScroll, ATTRIBUTE_LINE_POSITION},
+    path::{LinePath, PathSegment},
+    spline::Smoothing,
+    svg::{SvgError, SvgStyle},
+    svg_export::{SvgExportMode, world_to_svg},
 };
\ No newline at end of file
@@ -0,0 +1,98 @@
+use std::f32::consts::PI;
+
+use bevy::math::Vec2;
+
+/// Marker drawn at an end of an unconnected line.
+/// The stroke is shortened, so it ends inside the marker.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Marker {
+    pub shape: MarkerShape,
+    /// Size of the marker, relative to the width of the line
+    pub scale: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MarkerShape {
+    /// Filled triangle, with the tip at the end of the line
+    Arrow,
+    /// Two strokes meeting at the end of the line
+    OpenArrow,
+    /// Circle centered on the end of the line
+    Circle {
+        /// Number of vertices in the circle
+        resolution: usize,
+    },
+    /// Square centered on the end of the line
+    Square,
+    /// Square rotated 45°, centered on the end of the line
+    Diamond,
+    /// Bar across the end of the line
+    Bar,
+}
+
+impl Marker {
+    pub fn new(shape: MarkerShape, scale: f32) -> Self {
+        Marker { shape, scale }
+    }
+
+    /// How far the stroke is shortened, so it neither shows through nor leaves a gap.
+    pub(crate) fn setback(&self, width: f32) -> f32 {
+        let size = self.scale * width;
+        match self.shape {
+            MarkerShape::Arrow => size,
+            MarkerShape::OpenArrow => open_arrow_inset(width),
+            MarkerShape::Circle { .. } => (size * size / 4. - width * width / 4.).max(0.).sqrt(),
+            MarkerShape::Square => size / 2.,
+            MarkerShape::Diamond => (size / 2. - width / 2.).max(0.),
+            MarkerShape::Bar => width,
+        }
+    }
+
+    /// The outline going from the left side around to the right side, and the point it is drawn as a fan from.
+    /// X points forward from the end of the line, and Y to the left.
+    pub(crate) fn outline(&self, width: f32) -> (Vec<Vec2>, Vec2) {
+        let size = self.scale * width;
+        let half = size / 2.;
+        match self.shape {
+            MarkerShape::Arrow => (
+                vec![Vec2::new(-size, half), Vec2::ZERO, Vec2::new(-size, -half)],
+                Vec2::new(-size, 0.),
+            ),
+            MarkerShape::OpenArrow => {
+                // The inner edges are the outer edges, moved back along the line
+                let inset = Vec2::new(-open_arrow_inset(width), 0.);
+                (
+                    vec![Vec2::new(-size, half) + inset, Vec2::new(-size, half), Vec2::ZERO, Vec2::new(-size, -half), Vec2::new(-size, -half) + inset],
+                    inset,
+                )
+            },
+            MarkerShape::Circle { resolution } => {
+                let steps = 4.max(resolution);
+                // Starts and ends behind the center
+                let outline = (0..steps + 1).map(|i| {
+                    let angle = PI - i as f32 * 2. * PI / steps as f32;
+                    Vec2::from_angle(angle) * half
+                }).collect();
+                (outline, Vec2::ZERO)
+            },
+            MarkerShape::Square => (
+                vec![Vec2::new(-half, 0.), Vec2::new(-half, half), Vec2::new(half, half), Vec2::new(half, -half), Vec2::new(-half, -half), Vec2::new(-half, 0.)],
+                Vec2::ZERO,
+            ),
+            MarkerShape::Diamond => (
+                vec![Vec2::new(-half, 0.), Vec2::new(0., half), Vec2::new(half, 0.), Vec2::new(0., -half), Vec2::new(-half, 0.)],
+                Vec2::ZERO,
+            ),
+            MarkerShape::Bar => (
+                vec![Vec2::new(-width, 0.), Vec2::new(-width, half), Vec2::new(0., half), Vec2::new(0., -half), Vec2::new(-width, -half), Vec2::new(-width, 0.)],
+                Vec2::new(-width / 2., 0.),
+            ),
+        }
+    }
+}
+
+/// How far back the inner corner of an open arrow is, for strokes of the given width.
+/// The arms spread out with a slope of 1/2, so they are 1/sin(atan(1/2)) = √5 times the width apart along the line.
+fn open_arrow_inset(width: f32) -> f32 {
+    width * 5f32.sqrt()
+}
@@ -0,0 +1,135 @@
+use bevy::{
+    prelude::*,
+    utils::HashSet,
+    render::{
+        mesh::{MeshVertexAttribute, MeshVertexBufferLayoutRef},
+        render_resource::{AsBindGroup, RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError, VertexFormat},
+    },
+    sprite::{Material2d, Material2dKey},
+};
+
+pub(crate) const FLEX_LINE_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(0x3c1a9e05d2b84f7e9a61c0e8b5d27f43);
+
+/// Distance along the line in world units, and position across it from -1 on the left side to 1 on the right side.
+/// Caps fold around the end of the line, so the sides of caps are at -1 and 1 as well.
+/// The inside of a [`Fill`](crate::Fill) is at 2 across, where it has no edges, glow or dashes.
+pub const ATTRIBUTE_LINE_POSITION: MeshVertexAttribute =
+    MeshVertexAttribute::new("FlexLine_Position", 0x6f1e_2b9d_47c3_a805, VertexFormat::Float32x2);
+
+/// Position across the line of fill vertices, outside the line
+pub(crate) const FILL_ACROSS: f32 = 2.;
+
+/// Material for lines, drawing smooth edges from the position across the line.
+/// It can offset the texture coordinates, and draw dashes and a glow without rebuilding the mesh.
+/// The texture should use a repeating sampler, so it can be scrolled.
+#[derive(Asset, AsBindGroup, TypePath, Clone)]
+pub struct FlexLineMaterial {
+    #[uniform(0)]
+    pub color: LinearRgba,
+    /// Added to the texture coordinates. X is along the line, and Y across it
+    #[uniform(0)]
+    pub uv_offset: Vec2,
+    /// The part of the width that is drawn, from 0 to 1.
+    /// Lines can be made thinner this way, without rebuilding the mesh. The fringe of feathered lines counts as part of the width.
+    #[uniform(0)]
+    pub thickness: f32,
+    /// How far into the dashes the line starts
+    #[uniform(0)]
+    pub dash_offset: f32,
+    /// Lengths of the dashes and the gaps between them, in world units. There are no dashes while the gap is 0
+    #[uniform(0)]
+    pub dashes: Vec2,
+    /// Color under the line, at full strength under the drawn part, and fading out linearly from its edge to the sides.
+    /// Only visible where the line is translucent, or beyond the drawn part when `thickness` is below 1.
+    #[uniform(0)]
+    pub glow: LinearRgba,
+    #[texture(1)]
+    #[sampler(2)]
+    pub texture: Option<Handle<Image>>,
+}
+
+impl Default for FlexLineMaterial {
+    fn default() -> Self {
+        FlexLineMaterial {
+            color: LinearRgba::WHITE,
+            uv_offset: Vec2::ZERO,
+            thickness: 1.,
+            dash_offset: 0.,
+            dashes: Vec2::ZERO,
+            glow: LinearRgba::NONE,
+            texture: None,
+        }
+    }
+}
+
+impl From<Handle<Image>> for FlexLineMaterial {
+    fn from(texture: Handle<Image>) -> Self {
+        FlexLineMaterial {
+            texture: Some(texture),
+            ..Default::default()
+        }
+    }
+}
+
+impl Material2d for FlexLineMaterial {
+    fn vertex_shader() -> ShaderRef {
+        FLEX_LINE_SHADER_HANDLE.into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        FLEX_LINE_SHADER_HANDLE.into()
+    }
+
+    fn specialize(
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayoutRef,
+        _key: Material2dKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let vertex_layout = layout.0.get_layout(&[
+            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+            Mesh::ATTRIBUTE_UV_0.at_shader_location(1),
+            Mesh::ATTRIBUTE_COLOR.at_shader_location(2),
+            ATTRIBUTE_LINE_POSITION.at_shader_location(3),
+        ])?;
+        descriptor.vertex.buffers = vec![vertex_layout];
+        Ok(())
+    }
+}
+
+/// Scrolls the texture of a line with a [`FlexLineMaterial`], without rebuilding its mesh.
+/// The entity needs a `Handle<FlexLineMaterial>`, which [`FlexLine2dBundle`](crate::FlexLine2dBundle) has by default.
+/// Lines sharing a material scroll together, at the velocity of one of them.
+#[derive(Component, Clone, Copy)]
+pub struct TextureScroll {
+    /// In texture coordinates per second. X scrolls along the line, and Y across it.
+    /// Negative X scrolls towards the start of the line.
+    pub velocity: Vec2,
+}
+
+impl TextureScroll {
+    /// Scroll along the line, towards the end for positive speeds
+    pub fn along(speed: f32) -> Self {
+        TextureScroll {
+            velocity: Vec2::new(speed, 0.),
+        }
+    }
+}
+
+pub(crate) fn scroll_textures(
+    time: Res<Time>,
+    mut materials: ResMut<Assets<FlexLineMaterial>>,
+    query: Query<(&TextureScroll, &Handle<FlexLineMaterial>)>,
+) {
+    let delta = time.delta_seconds();
+    // Shared materials are only scrolled once per frame
+    let mut scrolled = HashSet::new();
+    for (scroll, handle) in query.iter() {
+        if !scrolled.insert(handle.id()) {
+            continue;
+        }
+        if let Some(material) = materials.get_mut(handle) {
+            // The texture repeats, so only the fraction is needed
+            material.uv_offset = (material.uv_offset - scroll.velocity * delta).rem_euclid(Vec2::ONE);
+        }
+    }
+}
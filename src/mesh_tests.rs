@@ -0,0 +1,332 @@
+//! Randomized tests of the meshes made by [`FlexLine::make_mesh`].
+//! Each case is made from a seed, which is printed when it fails.
+//! Set `FLEXLINE_SEED` to run a single seed again.
+
+use std::f32::consts::PI;
+
+use bevy::{prelude::*, render::mesh::VertexAttributeValues};
+
+use crate::{
+    Alignment, Band, Border, BorderPlacement, CapStyle, ConnectionStyle, CornerStyle, DashPattern, Fill, FillRule, FlexLine, Gradient, LineColor,
+    Marker, MarkerShape, Smoothing, Spread, StopUnits, UvMode, WidthProfile, ATTRIBUTE_LINE_POSITION,
+};
+
+const CASES: u64 = 256;
+
+/// Xorshift generator, so cases can be made again from their seed
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // Xorshift gets stuck at 0, and similar seeds should give different cases
+        Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Number in [0, 1)
+    fn unit(&mut self) -> f32 {
+        (self.next() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.unit()
+    }
+
+    fn index(&mut self, count: usize) -> usize {
+        (self.next() % count as u64) as usize
+    }
+
+    fn chance(&mut self, probability: f32) -> bool {
+        self.unit() < probability
+    }
+}
+
+/// The seeds to test, or only the one in `FLEXLINE_SEED`
+fn seeds() -> Vec<u64> {
+    match std::env::var("FLEXLINE_SEED") {
+        Ok(seed) => vec![seed.parse().expect("FLEXLINE_SEED should be a number")],
+        Err(_) => (0..CASES).collect(),
+    }
+}
+
+fn random_color(rng: &mut Rng) -> Color {
+    Color::srgba(rng.unit(), rng.unit(), rng.unit(), rng.range(0.2, 1.))
+}
+
+fn random_corner_style(rng: &mut Rng) -> CornerStyle {
+    match rng.index(5) {
+        0 => CornerStyle::Sharp,
+        1 => CornerStyle::Rounded { radius: rng.range(0., 3.), resolution: 4 + rng.index(28) },
+        2 => CornerStyle::Miter { limit: rng.range(1., 6.) },
+        3 => CornerStyle::Bevel,
+        _ => CornerStyle::Round { resolution: 4 + rng.index(28) },
+    }
+}
+
+fn random_alignment(rng: &mut Rng, max_offset: f32) -> Alignment {
+    match rng.index(4) {
+        0 => Alignment::Center,
+        1 => Alignment::LeftSide,
+        2 => Alignment::RightSide,
+        _ => Alignment::Offset(rng.range(-max_offset, max_offset)),
+    }
+}
+
+fn random_cap(rng: &mut Rng) -> CapStyle {
+    match rng.index(5) {
+        0 => CapStyle::Butt,
+        1 => CapStyle::Square,
+        2 => CapStyle::Round { resolution: 4 + rng.index(28) },
+        3 => CapStyle::Triangle,
+        _ => CapStyle::Custom(vec![Vec2::new(0., 1.), Vec2::new(rng.range(0.1, 2.), 0.), Vec2::new(0., -1.)]),
+    }
+}
+
+fn random_marker(rng: &mut Rng) -> Option<Marker> {
+    let shape = match rng.index(7) {
+        0 => MarkerShape::Arrow,
+        1 => MarkerShape::OpenArrow,
+        2 => MarkerShape::Circle { resolution: 4 + rng.index(28) },
+        3 => MarkerShape::Square,
+        4 => MarkerShape::Diamond,
+        5 => MarkerShape::Bar,
+        _ => return None,
+    };
+    Some(Marker::new(shape, rng.range(1., 4.)))
+}
+
+fn random_gradient(rng: &mut Rng) -> Gradient {
+    let mut position = 0.;
+    let stops = (0..1 + rng.index(4)).map(|_| {
+        position += rng.range(0., 0.5);
+        (position, random_color(rng))
+    }).collect();
+    Gradient::new(stops, [Spread::Clamp, Spread::Repeat, Spread::Mirror][rng.index(3)])
+}
+
+/// Any valid line, including repeated locations, sharp turns and every option
+fn random_line(rng: &mut Rng) -> FlexLine {
+    let count = 2 + rng.index(10);
+    let mut locations: Vec<Vec2> = Vec::with_capacity(count);
+    for _ in 0..count {
+        let location = match (locations.last(), rng.index(6)) {
+            (Some(last), 0) => *last,
+            (Some(last), 1) if locations.len() > 1 => {
+                // Straight on, or straight back
+                let before = locations[locations.len() - 2];
+                *last + (*last - before) * rng.range(-2., 2.)
+            },
+            _ => Vec2::new(rng.range(-20., 20.), rng.range(-20., 20.)),
+        };
+        locations.push(location);
+    }
+
+    let width = rng.range(0.1, 4.);
+    let color = match rng.index(5) {
+        0 => LineColor::Fill(random_color(rng)),
+        1 => LineColor::GradientAcross { left: random_color(rng), right: random_color(rng) },
+        2 => LineColor::PerVertex((0..count).map(|_| random_color(rng)).collect()),
+        3 => LineColor::GradientAcrossStops(random_gradient(rng)),
+        _ => {
+            let units = if rng.chance(0.5) { StopUnits::Normalized } else { StopUnits::Distance };
+            LineColor::GradientAlong { gradient: random_gradient(rng), units }
+        },
+    };
+    let connection_style = if rng.chance(0.3) { ConnectionStyle::Connected } else { ConnectionStyle::Unconnected };
+
+    let mut line = FlexLine::new(locations, width, random_alignment(rng, width), random_corner_style(rng), connection_style, color);
+    if rng.chance(0.3) {
+        line.widths = Some((0..count).map(|_| rng.range(0., 4.)).collect());
+    }
+    if rng.chance(0.2) {
+        line.width_profile = Some(WidthProfile::keys(vec![(0., rng.unit()), (0.5, rng.range(0., 2.)), (1., rng.unit())]));
+    }
+    if rng.chance(0.2) {
+        let tolerance = rng.range(0.01, 1.);
+        line.smoothing = Some(if rng.chance(0.5) { Smoothing::CatmullRom { tolerance } } else { Smoothing::BSpline { tolerance } });
+    }
+    if rng.chance(0.3) {
+        line.dash_pattern = Some(DashPattern::new(vec![rng.range(0.1, 5.), rng.range(0.1, 5.)], rng.range(-5., 5.)));
+    }
+    line.start_cap = random_cap(rng);
+    line.end_cap = random_cap(rng);
+    line.start_marker = random_marker(rng);
+    line.end_marker = random_marker(rng);
+    line.uv_mode = match rng.index(3) {
+        0 => UvMode::Stretch,
+        1 => UvMode::Repeat { length: rng.range(0.1, 10.) },
+        _ => UvMode::Normalized,
+    };
+    // Merging makes many small pieces, which is slow to check
+    line.merge_overlaps = rng.chance(0.1);
+    if rng.chance(0.3) {
+        line.feathering_width = Some(rng.range(0.1, 2.));
+    }
+    if rng.chance(0.3) {
+        let placement = [BorderPlacement::Inside, BorderPlacement::Outside, BorderPlacement::Both][rng.index(3)];
+        line.border = Some(Border::new(rng.range(0., 2.), random_color(rng), placement));
+    }
+    if rng.chance(0.3) {
+        line.bands = (0..1 + rng.index(5)).map(|_| match rng.index(3) {
+            0 => Band { fraction: rng.unit(), color: None, dash_pattern: None },
+            1 => Band::new(rng.unit(), random_color(rng)),
+            _ => Band::dashed(rng.unit(), random_color(rng), DashPattern::dashed(rng.range(0.1, 5.), rng.range(0.1, 5.))),
+        }).collect();
+    }
+    if rng.chance(0.3) {
+        let mut fill = match rng.index(2) {
+            0 => Fill::new(random_color(rng)),
+            _ => Fill::gradient(Vec2::from_angle(rng.range(-PI, PI)), random_color(rng), random_color(rng)),
+        };
+        fill.rule = if rng.chance(0.5) { FillRule::NonZero } else { FillRule::EvenOdd };
+        for _ in 0..rng.index(3) {
+            fill.holes.push((0..3 + rng.index(5)).map(|_| Vec2::new(rng.range(-20., 20.), rng.range(-20., 20.))).collect());
+        }
+        line.fill = Some(fill);
+    }
+    line
+}
+
+/// An open line with long segments and gentle turns, which covers about its length times its width
+fn gentle_line(rng: &mut Rng) -> FlexLine {
+    let width = rng.range(0.5, 2.);
+    let mut locations = vec![Vec2::ZERO];
+    let mut direction = rng.range(-PI, PI);
+    for _ in 0..1 + rng.index(5) {
+        direction += rng.range(-PI / 3., PI / 3.);
+        let step = Vec2::from_angle(direction) * width * rng.range(5., 15.);
+        locations.push(*locations.last().unwrap() + step);
+    }
+
+    let corner_style = match rng.index(4) {
+        0 => CornerStyle::Rounded { radius: rng.range(0., width), resolution: 32 },
+        1 => CornerStyle::Miter { limit: 4. },
+        2 => CornerStyle::Bevel,
+        _ => CornerStyle::Round { resolution: 32 },
+    };
+    // Offset no further than the sides, as corners change the area more the further the line is from the path
+    let mut line = FlexLine::new(locations, width, random_alignment(rng, width / 2.), corner_style, ConnectionStyle::Unconnected, LineColor::Fill(Color::WHITE));
+    line.start_cap = CapStyle::Butt;
+    line.end_cap = CapStyle::Butt;
+    line.merge_overlaps = rng.chance(0.5);
+    line
+}
+
+/// The attributes and triangles of a line mesh, as the tests look at them
+pub(crate) struct MeshParts {
+    pub positions: Vec<Vec2>,
+    pub colors: Vec<[f32; 4]>,
+    pub line_positions: Vec<[f32; 2]>,
+    pub indices: Vec<usize>,
+}
+
+impl MeshParts {
+    pub fn new(mesh: &Mesh) -> Self {
+        let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION).and_then(VertexAttributeValues::as_float3).unwrap_or_default();
+        let colors = match mesh.attribute(Mesh::ATTRIBUTE_COLOR) {
+            Some(VertexAttributeValues::Float32x4(colors)) => colors.clone(),
+            _ => Vec::new(),
+        };
+        let line_positions = match mesh.attribute(ATTRIBUTE_LINE_POSITION) {
+            Some(VertexAttributeValues::Float32x2(line_positions)) => line_positions.clone(),
+            _ => Vec::new(),
+        };
+        MeshParts {
+            positions: positions.iter().map(|p| Vec2::new(p[0], p[1])).collect(),
+            colors,
+            line_positions,
+            indices: mesh.indices().map(|indices| indices.iter().collect()).unwrap_or_default(),
+        }
+    }
+
+    /// The corners of each triangle
+    pub fn triangles(&self) -> impl Iterator<Item = [Vec2; 3]> + '_ {
+        self.indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]].map(|i| self.positions[i]))
+    }
+
+    /// Area covered by the triangles. Clockwise triangles count as negative
+    pub fn area(&self) -> f32 {
+        self.triangles().map(|[a, b, c]| (b - a).perp_dot(c - a) / 2.).sum()
+    }
+}
+
+/// Check the mesh for broken indices, attributes and triangles, and the strips it was made from for folds.
+/// Folds are counted in all meshes made on this thread since the last check. Returns the area it covers.
+fn check_mesh(mesh: &Mesh, seed: u64) -> f32 {
+    let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION).and_then(VertexAttributeValues::as_float3)
+        .unwrap_or_else(|| panic!("seed {seed}: no positions"));
+    let count = positions.len();
+    for attribute in [Mesh::ATTRIBUTE_COLOR, Mesh::ATTRIBUTE_UV_0, ATTRIBUTE_LINE_POSITION] {
+        let name = attribute.name;
+        let values = mesh.attribute(attribute).unwrap_or_else(|| panic!("seed {seed}: no {name}"));
+        assert_eq!(values.len(), count, "seed {seed}: {name} has a different length than the positions");
+    }
+    let parts = MeshParts::new(mesh);
+    assert!(parts.positions.iter().all(|p| p.is_finite()), "seed {seed}: position is not finite");
+    assert!(parts.colors.iter().flatten().all(|c| c.is_finite()), "seed {seed}: color is not finite");
+    if let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+        assert!(uvs.iter().flatten().all(|uv| uv.is_finite()), "seed {seed}: UV is not finite");
+    }
+
+    assert!(parts.indices.len() % 3 == 0, "seed {seed}: {} indices is not a whole number of triangles", parts.indices.len());
+    assert!(parts.indices.iter().all(|i| *i < count), "seed {seed}: index out of range");
+
+    // Triangles are turned counterclockwise as they are added, so folds in a strip only show in the order of its sections
+    let backward = crate::stroke::BACKWARD_SECTIONS.take();
+    assert_eq!(backward, 0, "seed {seed}: {backward} sections go back along the line");
+
+    let mut area = 0.;
+    for (t, [a, b, c]) in parts.triangles().enumerate() {
+        let cross = (b - a).perp_dot(c - a);
+        let longest = a.distance_squared(b).max(b.distance_squared(c)).max(c.distance_squared(a));
+        assert!(cross.abs() > 1e-6 * longest, "seed {seed}: triangle {t} has no area");
+        assert!(cross > 0., "seed {seed}: triangle {t} is clockwise");
+        area += cross / 2.;
+    }
+    area
+}
+
+#[test]
+fn test_random_lines() {
+    for seed in seeds() {
+        let line = random_line(&mut Rng::new(seed));
+        assert_eq!(line.validate(), Ok(()), "seed {seed}: generated an invalid line");
+        check_mesh(&line.make_mesh(), seed);
+    }
+}
+
+#[test]
+fn test_random_offsets() {
+    for seed in seeds() {
+        let mut rng = Rng::new(seed);
+        let line = random_line(&mut rng);
+        let offset = line.offset(rng.range(-5., 5.));
+        assert!(offset.locations.iter().all(|p| p.is_finite()), "seed {seed}: offset location is not finite");
+        // Offsets of tight lines can be cut away completely
+        if offset.locations.len() >= 2 {
+            assert_eq!(offset.validate(), Ok(()), "seed {seed}: offset an invalid line");
+        }
+        check_mesh(&offset.make_mesh(), seed);
+    }
+}
+
+#[test]
+fn test_random_coverage() {
+    for seed in seeds() {
+        let line = gentle_line(&mut Rng::new(seed));
+        let area = check_mesh(&line.make_mesh(), seed);
+
+        let length: f32 = line.locations.windows(2).map(|pair| pair[0].distance(pair[1])).sum();
+        let expected = length * line.width;
+        // Each corner adds or removes a little, depending on its style
+        let corners = line.locations.len() - 2;
+        let tolerance = (corners as f32 + 0.01) * line.width * line.width;
+        assert!((area - expected).abs() <= tolerance, "seed {seed}: area {area}, expected about {expected}");
+    }
+}
@@ -0,0 +1,283 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+
+use crate::{flex_line::FlexLine, stroke::MeshBuffers};
+
+/// Largest angle between the points of the arcs around the outside of corners
+const ARC_STEP: f32 = PI / 16.;
+
+/// Part of the distance that points of the offset path must keep from the original path.
+/// The chords of the arcs come a little closer than the distance.
+const CLOSE: f32 = 0.99;
+
+/// A point on an offset path, with the segment of the original path it comes from, and how far along it
+#[derive(Clone, Copy, Debug)]
+struct OffsetPoint {
+    point: Vec2,
+    index: usize,
+    t: f32,
+}
+
+impl FlexLine {
+    /// Copy of the line, moved sideways by `distance`. Positive distances move it to the right, like [`Alignment::Offset`](crate::Alignment::Offset).
+    /// Corners on the outside of turns are rounded. On the inside of turns too tight for the distance,
+    /// the copy would loop back on itself, and the loop is cut off.
+    /// Lines with fewer than 2 locations are returned unchanged.
+    pub fn offset(&self, distance: f32) -> FlexLine {
+        if self.locations.len() < 2 {
+            return self.clone();
+        }
+        // The border is added to the widths, which should stay as they are
+        let line = FlexLine { border: None, ..self.clone() };
+        let line = line.resolved().into_owned();
+        if line.locations.len() < 2 || distance == 0. {
+            return FlexLine { border: self.border, ..line };
+        }
+
+        let points = offset_points(&line.locations, line.is_connected(), distance);
+        FlexLine {
+            border: self.border,
+            ..line.resampled(points.into_iter().map(|p| (p.index, p.t, p.point)))
+        }
+    }
+
+    /// Copies of the line at each offset, in the style of the line given with the offset, like lanes or rails.
+    /// The copies get their locations from this line, so the styles should not have widths or colors for each location.
+    pub fn parallel(&self, copies: impl IntoIterator<Item = (f32, FlexLine)>) -> Vec<FlexLine> {
+        copies.into_iter().map(|(distance, style)| {
+            let offset = self.offset(distance);
+            FlexLine {
+                locations: offset.locations,
+                widths: None,
+                smoothing: None,
+                connection_style: offset.connection_style,
+                ..style
+            }
+        }).collect()
+    }
+
+    /// A single mesh with all the lines, so they can be drawn as one entity. Invalid lines are left out.
+    pub fn combined_mesh(lines: &[FlexLine]) -> Mesh {
+        let mut mesh = MeshBuffers::default().into_mesh();
+        for line in lines.iter().filter(|line| line.validate().is_ok()) {
+            mesh.merge(&line.make_mesh());
+        }
+        mesh
+    }
+}
+
+/// The path moved `distance` to the right, with arcs around the outside of corners and the loops on the inside cut off
+fn offset_points(locations: &[Vec2], closed: bool, distance: f32) -> Vec<OffsetPoint> {
+    let count = locations.len();
+    let segments: Vec<(Vec2, Vec2)> = if closed {
+        (0..count).map(|i| (locations[i], locations[(i + 1) % count])).collect()
+    } else {
+        locations.windows(2).map(|pair| (pair[0], pair[1])).collect()
+    };
+    let shift = |(a, b): (Vec2, Vec2)| -(b - a).normalize().perp() * distance;
+
+    // Closed paths go over the first segment again, for the corner at the first location
+    let order = (0..segments.len()).chain(closed.then_some(0));
+    let mut points: Vec<OffsetPoint> = Vec::new();
+    for (pass, index) in order.enumerate() {
+        let segment = &segments[index];
+        let (start, end) = (segment.0 + shift(*segment), segment.1 + shift(*segment));
+        let previous = (pass > 0).then(|| segments[(index + segments.len() - 1) % segments.len()]);
+
+        let mut start_point = OffsetPoint { point: start, index, t: 0. };
+        if let Some(previous) = previous {
+            let from = shift(previous);
+            let turn = from.angle_between(shift(*segment));
+            if turn * distance.signum() > 0. {
+                // Outside of the turn, going around the location
+                let steps = (turn.abs() / ARC_STEP).ceil() as usize;
+                points.extend((1..steps).map(|step| OffsetPoint {
+                    point: segment.0 + Vec2::from_angle(turn * step as f32 / steps as f32).rotate(from),
+                    index,
+                    t: 0.,
+                }));
+            } else if let [.., before, last] = points[..] {
+                // Inside of the turn, where the sides usually cross
+                if let Some((s, _)) = crossing(before.point, last.point, start, end) {
+                    let crossing = before.point.lerp(last.point, s);
+                    points.pop();
+                    start_point.point = crossing;
+                    start_point.t = (crossing - segment.0).dot(segment.1 - segment.0) / segment.0.distance_squared(segment.1);
+                }
+            }
+        }
+        points.push(start_point);
+        points.push(OffsetPoint { point: end, index, t: 1. });
+    }
+
+    if closed {
+        // Start and end halfway along the first segment, instead of at its corners, so loops can be cut at either end
+        let (a, b) = segments[0];
+        let middle = OffsetPoint { point: a.midpoint(b) + shift((a, b)), index: 0, t: 0.5 };
+        // Leave out the corner of the first pass, and the end of the second
+        let mut ring = vec![middle];
+        ring.extend(points.drain(1..points.len() - 1));
+        ring.push(middle);
+        let mut points = remove_loops(ring, &segments, distance.abs());
+        points.pop();
+        return points;
+    }
+
+    let mut points = remove_loops(points, &segments, distance.abs());
+    // Ends of the path on the inside of a turn, that do not cross the rest. Nothing may be left if the path is too tight.
+    let too_close = |p: Vec2| closest_distance(p, &segments) < distance.abs() * CLOSE;
+    let end_too_close = |a: &OffsetPoint, b: Option<&OffsetPoint>| too_close(a.point) || b.is_some_and(|b| too_close(a.point.midpoint(b.point)));
+    while let Some(last) = points.last() {
+        if !end_too_close(last, points.get(points.len().wrapping_sub(2))) {
+            break;
+        }
+        points.pop();
+    }
+    while let Some(first) = points.first() {
+        if !end_too_close(first, points.get(1)) {
+            break;
+        }
+        points.remove(0);
+    }
+    points
+}
+
+/// Cut off loops that come closer to the original path than the distance.
+/// Other loops are kept, as they follow loops in the original path.
+fn remove_loops(mut points: Vec<OffsetPoint>, segments: &[(Vec2, Vec2)], distance: f32) -> Vec<OffsetPoint> {
+    let too_close = |p: Vec2| closest_distance(p, segments) < distance * CLOSE;
+    // Crossings before a cut were kept, and cutting only takes points out of their loops,
+    // so the search goes on from the cut
+    let mut start = 0;
+    'search: loop {
+        for (i, j) in crossing_pairs(&points).into_iter().filter(|(i, _)| *i >= start) {
+            let Some((s, u)) = crossing(points[i].point, points[i + 1].point, points[j].point, points[j + 1].point) else {
+                continue;
+            };
+            let crossing = points[i].point.lerp(points[i + 1].point, s);
+            // The path from the crossing around the loop and back
+            let mut path = vec![crossing];
+            path.extend(points[i + 1..=j].iter().map(|p| p.point));
+            path.push(crossing);
+            if !path.iter().any(|p| too_close(*p)) && !path.windows(2).any(|pair| too_close(pair[0].midpoint(pair[1]))) {
+                continue;
+            }
+
+            let (a, b) = (points[j], points[j + 1]);
+            let t = if a.index == b.index { a.t + (b.t - a.t) * u } else { b.t };
+            points.splice(i + 1..=j, [OffsetPoint { point: crossing, index: b.index, t }]);
+            start = i;
+            continue 'search;
+        }
+        return points;
+    }
+}
+
+/// The segments of the path that cross, by the index of their first point.
+/// Sorted by the first segment, and then by the last one going backwards, so the largest loops are found first.
+fn crossing_pairs(points: &[OffsetPoint]) -> Vec<(usize, usize)> {
+    // Segments sorted by their left end, so only segments that overlap in X are tested against each other
+    let mut sorted: Vec<(f32, f32, usize)> = points.windows(2).enumerate()
+        .map(|(i, pair)| (pair[0].point.x.min(pair[1].point.x), pair[0].point.x.max(pair[1].point.x), i))
+        .collect();
+    sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut pairs = Vec::new();
+    for (k, (_, right, a)) in sorted.iter().enumerate() {
+        for (_, _, b) in sorted[k + 1..].iter().take_while(|(left, _, _)| left <= right) {
+            let (i, j) = (*a.min(b), *a.max(b));
+            if j < i + 2 || points[i].point == points[j + 1].point {
+                // Neighbours, or the ends of a closed path
+                continue;
+            }
+            if crossing(points[i].point, points[i + 1].point, points[j].point, points[j + 1].point).is_some() {
+                pairs.push((i, j));
+            }
+        }
+    }
+    pairs.sort_by_key(|(i, j)| (*i, std::cmp::Reverse(*j)));
+    pairs
+}
+
+/// Where the segments a-b and c-d cross, as how far along each they are
+fn crossing(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> Option<(f32, f32)> {
+    let (ab, cd) = (b - a, d - c);
+    let denominator = ab.perp_dot(cd);
+    if denominator.abs() <= 1e-6 * ab.length() * cd.length() {
+        return None;
+    }
+    let s = (c - a).perp_dot(cd) / denominator;
+    let u = (c - a).perp_dot(ab) / denominator;
+    ((0. ..=1.).contains(&s) && (0. ..=1.).contains(&u)).then_some((s, u))
+}
+
+/// Distance from the point to the closest of the segments
+fn closest_distance(point: Vec2, segments: &[(Vec2, Vec2)]) -> f32 {
+    segments.iter().map(|(a, b)| {
+        let t = ((point - *a).dot(*b - *a) / a.distance_squared(*b)).clamp(0., 1.);
+        point.distance(a.lerp(*b, t))
+    }).fold(f32::INFINITY, f32::min)
+}
+
+#[cfg(test)]
+use crate::{Alignment, ConnectionStyle, CornerStyle, LineColor};
+
+#[test]
+fn test_offset_square() {
+    // Counter-clockwise, so the right side is outside
+    let locations = vec![Vec2::new(0., 0.), Vec2::new(10., 0.), Vec2::new(10., 10.), Vec2::new(0., 10.)];
+    let square = FlexLine::new(locations, 1., Alignment::Center, CornerStyle::Sharp, ConnectionStyle::Connected, LineColor::Fill(Color::WHITE));
+    let segments: Vec<(Vec2, Vec2)> = (0..4).map(|i| (square.locations[i], square.locations[(i + 1) % 4])).collect();
+
+    let outside = square.offset(1.);
+    assert!(outside.locations.len() > 8);
+    assert!(outside.locations.iter().any(|p| p.distance(Vec2::new(10., -1.)) < 1e-4));
+    assert!(outside.locations.iter().any(|p| p.distance(Vec2::new(11., 0.)) < 1e-4));
+    assert!(outside.locations.iter().all(|p| (closest_distance(*p, &segments) - 1.).abs() < 1e-4));
+
+    let inside = square.offset(-2.);
+    for corner in [Vec2::new(2., 2.), Vec2::new(8., 2.), Vec2::new(8., 8.), Vec2::new(2., 8.)] {
+        assert!(inside.locations.iter().any(|p| p.distance(corner) < 1e-4));
+    }
+    assert!(inside.locations.iter().all(|p| (closest_distance(*p, &segments) - 2.).abs() < 1e-4));
+}
+
+#[test]
+fn test_offset_tight_turn() {
+    // A spike, where the inner copy would go past the tip and loop back on itself
+    let locations = vec![Vec2::new(0., 0.), Vec2::new(10., 0.), Vec2::new(0., 5.)];
+    let line = FlexLine::new(locations, 0.5, Alignment::Center, CornerStyle::Sharp, ConnectionStyle::Unconnected, LineColor::Fill(Color::WHITE));
+    let segments: Vec<(Vec2, Vec2)> = line.locations.windows(2).map(|pair| (pair[0], pair[1])).collect();
+
+    let inside = line.offset(-1.);
+    assert_eq!(inside.locations.len(), 3);
+    assert!(inside.locations.iter().all(|p| closest_distance(*p, &segments) > 1. - 1e-4));
+    assert!(inside.locations.iter().all(|p| p.x < 10.));
+
+    let copies = line.parallel([(-1., line.clone()), (1., line.clone())]);
+    let mesh = FlexLine::combined_mesh(&copies);
+    let count = copies.iter().map(|copy| copy.make_mesh().count_vertices()).sum::<usize>();
+    assert_eq!(mesh.count_vertices(), count);
+}
+
+#[test]
+fn test_offset_circle() {
+    // Many segments, without any loops to cut
+    let locations: Vec<Vec2> = (0..2000).map(|i| Vec2::from_angle(i as f32 / 2000. * 2. * PI) * 100.).collect();
+    let circle = FlexLine::new(locations, 1., Alignment::Center, CornerStyle::Sharp, ConnectionStyle::Connected, LineColor::Fill(Color::WHITE));
+    for (distance, radius) in [(5., 105.), (-5., 95.)] {
+        let offset = circle.offset(distance);
+        assert!(offset.locations.len() >= 2000);
+        assert!(offset.locations.iter().all(|p| (p.length() - radius).abs() < 0.01));
+    }
+}
+
+#[test]
+fn test_offset_too_few_locations() {
+    // Resolving the width profile needs at least 2 locations
+    let line = FlexLine { width_profile: Some(crate::WidthProfile::keys(vec![(0., 1.), (1., 0.)])), ..default() };
+    assert!(line.offset(1.).locations.is_empty());
+    let copies = line.parallel([(1., FlexLine::default())]);
+    assert!(copies[0].locations.is_empty());
+}
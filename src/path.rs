@@ -0,0 +1,215 @@
+use bevy::math::Vec2;
+
+use crate::flex_line::{ConnectionStyle, FlexLine};
+
+/// Smallest tolerance used when flattening. Smaller, zero, negative and NaN tolerances are raised to it
+const MIN_TOLERANCE: f32 = 1e-4;
+/// Most points a single curve is flattened into
+const MAX_STEPS: f32 = 10000.;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathSegment {
+    /// Start a new subpath
+    MoveTo(Vec2),
+    LineTo(Vec2),
+    QuadraticTo {
+        control: Vec2,
+        to: Vec2,
+    },
+    CubicTo {
+        control1: Vec2,
+        control2: Vec2,
+        to: Vec2,
+    },
+    /// Connect the subpath back to where it started
+    Close,
+}
+
+/// A path of straight and curved segments, which can be flattened into lines.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LinePath {
+    pub segments: Vec<PathSegment>,
+}
+
+/// A flattened subpath
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Polyline {
+    pub points: Vec<Vec2>,
+    pub closed: bool,
+}
+
+impl LinePath {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn move_to(mut self, to: Vec2) -> Self {
+        self.segments.push(PathSegment::MoveTo(to));
+        self
+    }
+
+    pub fn line_to(mut self, to: Vec2) -> Self {
+        self.segments.push(PathSegment::LineTo(to));
+        self
+    }
+
+    pub fn quadratic_to(mut self, control: Vec2, to: Vec2) -> Self {
+        self.segments.push(PathSegment::QuadraticTo { control, to });
+        self
+    }
+
+    pub fn cubic_to(mut self, control1: Vec2, control2: Vec2, to: Vec2) -> Self {
+        self.segments.push(PathSegment::CubicTo { control1, control2, to });
+        self
+    }
+
+    pub fn close(mut self) -> Self {
+        self.segments.push(PathSegment::Close);
+        self
+    }
+
+    /// Make a line for each subpath, with the style of `template`.
+    /// Curves are flattened, so no point on them is further than `tolerance` from the line.
+    /// Tolerances below 0.0001, including zero, negative and NaN tolerances, are raised to 0.0001.
+    /// Closed subpaths become [`ConnectionStyle::Connected`] lines.
+    pub fn to_lines(&self, tolerance: f32, template: &FlexLine) -> Vec<FlexLine> {
+        self.flatten(tolerance).into_iter().map(|polyline| FlexLine {
+            locations: polyline.points,
+            connection_style: if polyline.closed {
+                ConnectionStyle::Connected
+            } else {
+                ConnectionStyle::Unconnected
+            },
+            ..template.clone()
+        }).collect()
+    }
+
+    /// Flatten the path into polylines, one for each subpath with at least 2 points.
+    pub(crate) fn flatten(&self, tolerance: f32) -> Vec<Polyline> {
+        let tolerance = tolerance.max(MIN_TOLERANCE);
+        let mut polylines = Vec::new();
+        let mut points: Vec<Vec2> = Vec::new();
+        let mut current = Vec2::ZERO;
+
+        for segment in &self.segments {
+            if points.is_empty() {
+                points.push(current);
+            }
+
+            match *segment {
+                PathSegment::MoveTo(to) => {
+                    finish_subpath(&mut polylines, &mut points, false);
+                    points.push(to);
+                    current = to;
+                },
+                PathSegment::LineTo(to) => {
+                    points.push(to);
+                    current = to;
+                },
+                PathSegment::QuadraticTo { control, to } => {
+                    // The second derivative is constant, 2 * (p0 - 2 * p1 + p2).
+                    // Error of a chord over a parameter step h is at most h² * |B''| / 8
+                    let bend = (current - 2. * control + to).length();
+                    let steps = flatten_steps(bend / (4. * tolerance));
+                    let from = current;
+                    points.extend((1..steps + 1).map(|i| {
+                        let t = i as f32 / steps as f32;
+                        from * (1. - t) * (1. - t) + control * 2. * t * (1. - t) + to * t * t
+                    }));
+                    current = to;
+                },
+                PathSegment::CubicTo { control1, control2, to } => {
+                    // The second derivative is at most 6 * max(|p0 - 2 * p1 + p2|, |p1 - 2 * p2 + p3|)
+                    let bend = (current - 2. * control1 + control2).length()
+                        .max((control1 - 2. * control2 + to).length());
+                    let steps = flatten_steps(3. * bend / (4. * tolerance));
+                    let from = current;
+                    points.extend((1..steps + 1).map(|i| {
+                        let t = i as f32 / steps as f32;
+                        let mt = 1. - t;
+                        from * mt * mt * mt + control1 * 3. * mt * mt * t + control2 * 3. * mt * t * t + to * t * t * t
+                    }));
+                    current = to;
+                },
+                PathSegment::Close => {
+                    // The next subpath starts where this one did
+                    current = points[0];
+                    finish_subpath(&mut polylines, &mut points, true);
+                },
+            }
+        }
+        finish_subpath(&mut polylines, &mut points, false);
+
+        polylines
+    }
+}
+
+/// Number of steps for a curve, where the chord error over a step is `error / steps²`
+fn flatten_steps(error: f32) -> usize {
+    (error.sqrt().ceil().min(MAX_STEPS) as usize).max(1)
+}
+
+fn finish_subpath(polylines: &mut Vec<Polyline>, points: &mut Vec<Vec2>, closed: bool) {
+    let mut points = std::mem::take(points);
+    if closed && points.len() > 2 && points.first() == points.last() {
+        // The connection back to the start is implied
+        points.pop();
+    }
+    if points.len() >= 2 {
+        polylines.push(Polyline { points, closed });
+    }
+}
+
+#[test]
+fn test_flatten_subpaths() {
+    let path = LinePath::new()
+        .move_to(Vec2::new(0., 0.))
+        .line_to(Vec2::new(1., 0.))
+        .line_to(Vec2::new(1., 1.))
+        .line_to(Vec2::new(0., 0.))
+        .close()
+        .move_to(Vec2::new(5., 5.))
+        .move_to(Vec2::new(2., 2.))
+        .line_to(Vec2::new(3., 2.));
+
+    let polylines = path.flatten(0.1);
+    assert_eq!(polylines, vec![
+        Polyline { points: vec![Vec2::new(0., 0.), Vec2::new(1., 0.), Vec2::new(1., 1.)], closed: true },
+        Polyline { points: vec![Vec2::new(2., 2.), Vec2::new(3., 2.)], closed: false },
+    ]);
+}
+
+#[test]
+fn test_flatten_tolerance() {
+    let path = LinePath::new()
+        .move_to(Vec2::new(-10., 0.))
+        .cubic_to(Vec2::new(-10., 20.), Vec2::new(10., 20.), Vec2::new(10., 0.));
+    let exact = |t: f32| Vec2::new(-10., 0.) * (1. - t).powi(3) + Vec2::new(-10., 20.) * 3. * (1. - t).powi(2) * t
+        + Vec2::new(10., 20.) * 3. * (1. - t) * t * t + Vec2::new(10., 0.) * t.powi(3);
+
+    for tolerance in [1., 0.1, 0.01] {
+        let points = &path.flatten(tolerance)[0].points;
+        for i in 0..=1000 {
+            let point = exact(i as f32 / 1000.);
+            let distance = points.windows(2)
+                .map(|w| {
+                    let t = ((point - w[0]).dot(w[1] - w[0]) / w[0].distance_squared(w[1])).clamp(0., 1.);
+                    point.distance(w[0].lerp(w[1], t))
+                })
+                .fold(f32::MAX, f32::min);
+            assert!(distance <= tolerance, "{distance} > {tolerance}");
+        }
+    }
+}
+
+#[test]
+fn test_flatten_invalid_tolerance() {
+    let path = LinePath::new()
+        .move_to(Vec2::new(0., 0.))
+        .quadratic_to(Vec2::new(5., 10.), Vec2::new(10., 0.));
+
+    for tolerance in [0., -1., f32::NAN] {
+        let points = &path.flatten(tolerance)[0].points;
+        assert_eq!(points, &path.flatten(MIN_TOLERANCE)[0].points);
+    }
+}
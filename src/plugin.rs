@@ -1,22 +1,33 @@
-use bevy::{prelude::*, sprite::Mesh2dHandle};
+use bevy::{asset::load_internal_asset, prelude::*, sprite::{Material2dPlugin, Mesh2dHandle}};
 
 use super::*;
+use crate::material::{scroll_textures, FLEX_LINE_SHADER_HANDLE};
 
 pub struct FlexLine2dPlugin;
 
 impl Plugin for FlexLine2dPlugin {
     fn build(&self, app: &mut App) {
+        load_internal_asset!(app, FLEX_LINE_SHADER_HANDLE, "flex_line_material.wgsl", Shader::from_wgsl);
+        app.add_plugins(Material2dPlugin::<FlexLineMaterial>::default());
+
         app.add_systems(PostUpdate, update_lines);
+        app.add_systems(Update, scroll_textures);
 
-        app.world_mut().resource_mut::<Assets<ColorMaterial>>().insert(&BASE_MATERIAL_HANDLE, ColorMaterial::from_color(bevy::color::palettes::basic::WHITE));
+        app.world_mut().resource_mut::<Assets<FlexLineMaterial>>().insert(&BASE_MATERIAL_HANDLE, FlexLineMaterial::default());
     }
 }
 
 fn update_lines(
     mut meshes: ResMut<Assets<Mesh>>,
-    mut query: Query<(&FlexLine, &mut Mesh2dHandle), Changed<FlexLine>>,
+    mut query: Query<(Entity, &FlexLine, &mut Mesh2dHandle), Changed<FlexLine>>,
 ) {
-    for (poly, mut mesh) in query.iter_mut() {
-        mesh.0 = meshes.add(poly.make_mesh());
+    for (entity, poly, mut mesh) in query.iter_mut() {
+        match poly.validate() {
+            Ok(()) => mesh.0 = meshes.add(poly.make_mesh()),
+            Err(error) => {
+                warn!("Skipping invalid line on {entity}: {error}");
+                *mesh = Mesh2dHandle::default();
+            },
+        }
     }
 }
\ No newline at end of file
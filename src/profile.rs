@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+/// Scales the width of a line along its length.
+/// Positions are normalized, going from 0 at the first location to 1 at the end of the line.
+#[derive(Clone)]
+pub enum WidthProfile {
+    /// Width factors at positions sorted in increasing order, interpolated linearly in between.
+    Keys(Vec<(f32, f32)>),
+    /// Width factor as a function of the position.
+    /// Sampled densely enough, that the line is within `tolerance` of the function.
+    Function {
+        function: Arc<dyn Fn(f32) -> f32 + Send + Sync>,
+        tolerance: f32,
+    },
+}
+
+impl WidthProfile {
+    pub fn keys(mut keys: Vec<(f32, f32)>) -> Self {
+        keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+        WidthProfile::Keys(keys)
+    }
+
+    pub fn function(function: impl Fn(f32) -> f32 + Send + Sync + 'static) -> Self {
+        WidthProfile::Function {
+            function: Arc::new(function),
+            tolerance: 0.01,
+        }
+    }
+
+    /// The width factor at the given position
+    pub fn factor(&self, position: f32) -> f32 {
+        match self {
+            WidthProfile::Keys(keys) => {
+                let next = keys.partition_point(|(p, _)| *p <= position);
+                match (keys.get(next.wrapping_sub(1)), keys.get(next)) {
+                    (Some(a), Some(b)) if b.0 > a.0 => a.1 + (b.1 - a.1) * (position - a.0) / (b.0 - a.0),
+                    (Some(a), _) => a.1,
+                    (None, Some(b)) => b.1,
+                    (None, None) => 1.,
+                }
+            },
+            WidthProfile::Function { function, .. } => function(position),
+        }
+    }
+
+    /// Whether the keys are finite with factors that are not negative, or the tolerance of a function is finite and positive.
+    /// Functions themselves are not checked.
+    pub(crate) fn is_valid(&self) -> bool {
+        match self {
+            WidthProfile::Keys(keys) => keys.iter().all(|(position, factor)| position.is_finite() && factor.is_finite() && *factor >= 0.),
+            WidthProfile::Function { tolerance, .. } => tolerance.is_finite() && *tolerance > 0.,
+        }
+    }
+
+    /// Positions where the line must have a location, for the width to follow the profile.
+    pub(crate) fn subdivisions(&self) -> Vec<f32> {
+        match self {
+            WidthProfile::Keys(keys) => keys.iter()
+                .map(|(p, _)| *p)
+                .filter(|p| *p > 0. && *p < 1.)
+                .collect(),
+            WidthProfile::Function { tolerance, .. } => {
+                const INITIAL: usize = 16;
+                let mut positions = Vec::new();
+                for i in 0..INITIAL {
+                    if i > 0 {
+                        positions.push(i as f32 / INITIAL as f32);
+                    }
+                    self.subdivide(i as f32 / INITIAL as f32, (i + 1) as f32 / INITIAL as f32, *tolerance, 8, &mut positions);
+                }
+                positions.sort_by(f32::total_cmp);
+                positions
+            },
+        }
+    }
+
+    /// Bisect the interval until the middle is close enough to the interpolated value.
+    fn subdivide(&self, from: f32, to: f32, tolerance: f32, depth: usize, positions: &mut Vec<f32>) {
+        let mid = (from + to) / 2.;
+        let interpolated = (self.factor(from) + self.factor(to)) / 2.;
+        if depth == 0 || (self.factor(mid) - interpolated).abs() <= tolerance {
+            return;
+        }
+        positions.push(mid);
+        self.subdivide(from, mid, tolerance, depth - 1, positions);
+        self.subdivide(mid, to, tolerance, depth - 1, positions);
+    }
+}
+
+#[test]
+fn test_keys_factor() {
+    let profile = WidthProfile::keys(vec![(1., 0.), (0.25, 1.)]);
+    assert_eq!(profile.factor(0.), 1.);
+    assert_eq!(profile.factor(0.25), 1.);
+    assert_eq!(profile.factor(0.625), 0.5);
+    assert_eq!(profile.factor(2.), 0.);
+}
+
+#[test]
+fn test_function_subdivisions() {
+    let linear = WidthProfile::function(|p| 1. - p);
+    assert_eq!(linear.subdivisions().len(), 15);
+
+    let curved = WidthProfile::function(|p| (p * 10.).sin());
+    assert!(curved.subdivisions().len() > 15);
+}
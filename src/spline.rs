@@ -0,0 +1,153 @@
+use bevy::math::Vec2;
+
+use crate::vector_utils::project_point_onto_line;
+
+/// Curves through or near the locations of a line, replacing the straight segments.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Smoothing {
+    /// Centripetal Catmull-Rom spline, going through every location.
+    /// Unlike the uniform variant, it has no cusps or loops within a segment.
+    CatmullRom {
+        /// Maximum distance between the curve and the line drawn for it
+        tolerance: f32,
+    },
+    /// Uniform cubic B-spline, which is smoother, but only goes through the ends of unconnected lines.
+    BSpline {
+        /// Maximum distance between the curve and the line drawn for it
+        tolerance: f32,
+    },
+}
+
+/// How many times a piece of a segment can be split in half
+const MAX_DEPTH: usize = 10;
+
+impl Smoothing {
+    /// Sample the curve through the points. Segments are split where the curve bends,
+    /// until the middle of each piece is within the tolerance of the straight line.
+    /// Each sample has the index of the location it comes after, how far it is towards the next one, and its position.
+    pub(crate) fn sample(&self, points: &[Vec2], closed: bool) -> Vec<(usize, f32, Vec2)> {
+        let count = points.len();
+        if count < 3 {
+            return points.iter().enumerate().map(|(i, p)| (i, 0., *p)).collect();
+        }
+
+        // Unconnected lines get mirrored points beyond their ends, so the curve ends straight
+        let point = |i: isize| -> Vec2 {
+            if closed {
+                points[i.rem_euclid(count as isize) as usize]
+            } else if i < 0 {
+                2. * points[0] - points[1]
+            } else if i >= count as isize {
+                2. * points[count - 1] - points[count - 2]
+            } else {
+                points[i as usize]
+            }
+        };
+
+        let segments = if closed { count } else { count - 1 };
+        let mut samples = Vec::new();
+        for i in 0..segments {
+            let control = [-1, 0, 1, 2].map(|offset| point(i as isize + offset));
+            let curve = |t: f32| self.evaluate(control, t);
+
+            // Always split once, so S-shaped segments are not mistaken for straight ones
+            samples.push((i, 0., curve(0.)));
+            self.subdivide(&curve, i, 0., 0.5, MAX_DEPTH, &mut samples);
+            samples.push((i, 0.5, curve(0.5)));
+            self.subdivide(&curve, i, 0.5, 1., MAX_DEPTH, &mut samples);
+
+            if !closed && i == segments - 1 {
+                samples.push((count - 1, 0., curve(1.)));
+            }
+        }
+        samples
+    }
+
+    pub(crate) fn tolerance(&self) -> f32 {
+        match self {
+            Smoothing::CatmullRom { tolerance } | Smoothing::BSpline { tolerance } => *tolerance,
+        }
+    }
+
+    /// Add samples strictly between `from` and `to`, in order.
+    fn subdivide(&self, curve: &impl Fn(f32) -> Vec2, segment: usize, from: f32, to: f32, depth: usize, samples: &mut Vec<(usize, f32, Vec2)>) {
+        let mid = (from + to) / 2.;
+        let (start, end, middle) = (curve(from), curve(to), curve(mid));
+        let deviation = if start.distance_squared(end) > 0. {
+            middle.distance(project_point_onto_line(middle, start, end))
+        } else {
+            middle.distance(start)
+        };
+        if depth == 0 || deviation <= self.tolerance() {
+            return;
+        }
+
+        self.subdivide(curve, segment, from, mid, depth - 1, samples);
+        samples.push((segment, mid, middle));
+        self.subdivide(curve, segment, mid, to, depth - 1, samples);
+    }
+
+    /// Point on the curve between the 2 middle control points
+    fn evaluate(&self, [p0, p1, p2, p3]: [Vec2; 4], t: f32) -> Vec2 {
+        match self {
+            Smoothing::CatmullRom { .. } => {
+                // Knots spaced by the square root of the distance, with the pyramid formulation by Barry and Goldman
+                let knot = |a: Vec2, b: Vec2| a.distance(b).sqrt().max(1e-4);
+                let t1 = knot(p0, p1);
+                let t2 = t1 + knot(p1, p2);
+                let t3 = t2 + knot(p2, p3);
+                let u = t1 + (t2 - t1) * t;
+
+                let a1 = p0 * (t1 - u) / t1 + p1 * u / t1;
+                let a2 = p1 * (t2 - u) / (t2 - t1) + p2 * (u - t1) / (t2 - t1);
+                let a3 = p2 * (t3 - u) / (t3 - t2) + p3 * (u - t2) / (t3 - t2);
+                let b1 = a1 * (t2 - u) / t2 + a2 * u / t2;
+                let b2 = a2 * (t3 - u) / (t3 - t1) + a3 * (u - t1) / (t3 - t1);
+                b1 * (t2 - u) / (t2 - t1) + b2 * (u - t1) / (t2 - t1)
+            },
+            Smoothing::BSpline { .. } => {
+                let (t2, t3) = (t * t, t * t * t);
+                let mt = 1. - t;
+                (p0 * mt * mt * mt
+                    + p1 * (3. * t3 - 6. * t2 + 4.)
+                    + p2 * (-3. * t3 + 3. * t2 + 3. * t + 1.)
+                    + p3 * t3) / 6.
+            },
+        }
+    }
+}
+
+#[test]
+fn test_catmull_rom_interpolates() {
+    let points = [Vec2::new(0., 0.), Vec2::new(10., 0.), Vec2::new(10., 10.), Vec2::new(0., 10.)];
+    let samples = Smoothing::CatmullRom { tolerance: 0.05 }.sample(&points, false);
+
+    for (i, point) in points.iter().enumerate() {
+        let sample = samples.iter().find(|s| s.0 == i && s.1 == 0.).unwrap();
+        assert!(sample.2.distance(*point) < 1e-4);
+    }
+    assert!(samples.len() > 3 * 4);
+}
+
+#[test]
+fn test_closed_seam_is_smooth() {
+    let points = [Vec2::new(0., 0.), Vec2::new(10., 0.), Vec2::new(10., 10.), Vec2::new(0., 3.)];
+    for smoothing in [Smoothing::CatmullRom { tolerance: 0.01 }, Smoothing::BSpline { tolerance: 0.01 }] {
+        // The last segment ends where the first starts, going in the same direction
+        let first = [3, 0, 1, 2].map(|i| points[i]);
+        let last = [2, 3, 0, 1].map(|i| points[i]);
+        let h = 1e-3;
+        let outgoing = smoothing.evaluate(first, h) - smoothing.evaluate(first, 0.);
+        let incoming = smoothing.evaluate(last, 1.) - smoothing.evaluate(last, 1. - h);
+        assert!(smoothing.evaluate(first, 0.).distance(smoothing.evaluate(last, 1.)) < 1e-4);
+        assert!(outgoing.angle_between(incoming).abs() < 0.01);
+    }
+}
+
+#[test]
+fn test_b_spline_ends() {
+    let points = [Vec2::new(0., 0.), Vec2::new(10., 5.), Vec2::new(20., 0.)];
+    let samples = Smoothing::BSpline { tolerance: 0.05 }.sample(&points, false);
+    assert!(samples[0].2.distance(points[0]) < 1e-4);
+    assert!(samples[samples.len() - 1].2.distance(points[2]) < 1e-4);
+}
@@ -0,0 +1,495 @@
+use bevy::{prelude::*, render::{mesh::{self, PrimitiveTopology}, render_asset::RenderAssetUsages}};
+
+use crate::{band::Band, border::Border, fill::Fill, flex_line::{LineColor, UvMode}, material::{ATTRIBUTE_LINE_POSITION, FILL_ACROSS}, sweep};
+
+/// A cross section of a stroke, going from the left to the right side of the line.
+/// Consecutive sections are connected by quads.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Section {
+    pub left: Vec2,
+    pub mid: Vec2,
+    pub right: Vec2,
+    /// Point on the path at `distance`. Sections at corners lean, so it can be off the line between the sides
+    pub center: Vec2,
+    /// Direction of travel through the section
+    pub tangent: Vec2,
+    /// Arc length along the path at `mid`
+    pub distance: f32,
+    /// Position along `locations`, as a fractional index
+    pub param: f32,
+    /// Half the width of the line at the section
+    pub half_width: f32,
+    /// Whether the section folds around `mid`, as in caps.
+    /// The distance along the line then changes across the section.
+    pub folded: bool,
+}
+
+impl Section {
+    pub fn lerp(&self, other: &Section, t: f32) -> Section {
+        Section {
+            left: self.left.lerp(other.left, t),
+            mid: self.mid.lerp(other.mid, t),
+            right: self.right.lerp(other.right, t),
+            center: self.center.lerp(other.center, t),
+            tangent: self.tangent.lerp(other.tangent, t).normalize_or(self.tangent),
+            distance: self.distance + (other.distance - self.distance) * t,
+            param: self.param + (other.param - self.param) * t,
+            half_width: self.half_width + (other.half_width - self.half_width) * t,
+            folded: self.folded,
+        }
+    }
+
+    /// The section at a distance between this one and the next.
+    /// Between corners, the cut goes straight across the path, with each side cut where it is level with the path.
+    /// Lerping would make a slanted cut there, as the sections at corners lean towards their miter or bevel.
+    /// Within a corner, where the sections fan out, they are lerped.
+    pub fn cut(&self, next: &Section, distance: f32) -> Section {
+        let span = next.distance - self.distance;
+        if span <= f32::EPSILON {
+            return *next;
+        }
+        let t = ((distance - self.distance) / span).clamp(0., 1.);
+        let lerped = self.lerp(next, t);
+        let direction = (next.center - self.center).normalize_or_zero();
+        if self.folded || next.folded || self.param == next.param || direction == Vec2::ZERO {
+            return lerped;
+        }
+
+        let center = self.center + direction * (distance - self.distance);
+        let side = |a: Vec2, b: Vec2| {
+            let along = (b - a).dot(direction);
+            if along <= f32::EPSILON {
+                return a.lerp(b, t);
+            }
+            a + (b - a) * ((center - a).dot(direction) / along)
+        };
+        let (left, right) = (side(self.left, next.left), side(self.right, next.right));
+        Section {
+            left,
+            mid: left.midpoint(right),
+            right,
+            center,
+            tangent: direction,
+            ..lerped
+        }
+    }
+
+    /// The point across the section. -1 is the left side, 0 the middle and 1 the right side.
+    /// The middle is not necessarily halfway, as caps fold around it.
+    pub fn point(&self, across: f32) -> Vec2 {
+        if across < 0. {
+            self.mid.lerp(self.left, -across)
+        } else {
+            self.mid.lerp(self.right, across)
+        }
+    }
+
+    /// Distance along the line at a point on the section
+    pub fn distance_at(&self, point: Vec2) -> f32 {
+        if self.folded {
+            self.distance + (point - self.mid).dot(self.tangent)
+        } else {
+            self.distance
+        }
+    }
+
+    /// The point a distance beyond the side of the section, going away from the middle.
+    /// `side` is -1 for the left side and 1 for the right side.
+    pub fn beyond(&self, side: f32, distance: f32) -> Vec2 {
+        let point = self.point(side);
+        let outward = (point - self.mid).normalize_or(self.tangent.perp() * -side);
+        point + outward * distance
+    }
+
+    /// The same section, moved along the line, where the offset is parallel to the tangent.
+    pub fn moved(&self, offset: Vec2) -> Section {
+        Section {
+            left: self.left + offset,
+            mid: self.mid + offset,
+            right: self.right + offset,
+            center: self.center + offset,
+            distance: self.distance + offset.dot(self.tangent),
+            ..*self
+        }
+    }
+
+    /// The same section, seen when travelling the other way.
+    pub fn flipped(&self) -> Section {
+        Section {
+            left: self.right,
+            right: self.left,
+            tangent: -self.tangent,
+            ..*self
+        }
+    }
+}
+
+/// A sequence of sections, making up a continuous part of a stroke.
+#[derive(Clone, Default)]
+pub(crate) struct Strip {
+    pub sections: Vec<Section>,
+    /// Whether the last section meets the first, so the strip has no ends
+    pub closed: bool,
+}
+
+impl Strip {
+    /// The section at the given distance, cut between the surrounding sections.
+    /// Where a corner has several sections at the distance, the cut is on the segment after the corner,
+    /// so a slice starting there begins after the last of them. With `before`, it is on the segment before the corner instead.
+    fn section_at(&self, distance: f32, before: bool) -> Section {
+        let sections = &self.sections;
+        let next = if before {
+            sections.partition_point(|s| s.distance < distance)
+        } else {
+            sections.partition_point(|s| s.distance <= distance)
+        };
+        let next = next.clamp(1, sections.len() - 1);
+        sections[next - 1].cut(&sections[next], distance)
+    }
+
+    /// Cut out the part of the strip between the distances `from` and `to`.
+    pub fn slice(&self, from: f32, to: f32) -> Strip {
+        if self.sections.len() < 2 {
+            return self.clone();
+        }
+
+        let mut sections = vec![self.section_at(from, false)];
+        sections.extend(self.sections.iter().filter(|s| s.distance > from && s.distance < to));
+        sections.push(self.section_at(to, true));
+        Strip { sections, closed: false }
+    }
+
+    /// Copy of the strip with sections added at the distances, which must be sorted.
+    /// Folded sections are left as they are.
+    pub fn subdivided(&self, at: &[f32]) -> Strip {
+        let mut sections = Vec::with_capacity(self.sections.len() + at.len());
+        let mut next_at = 0;
+        for pair in self.sections.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            sections.push(*a);
+            while next_at < at.len() && at[next_at] <= a.distance {
+                next_at += 1;
+            }
+            while next_at < at.len() && at[next_at] < b.distance {
+                if !a.folded && !b.folded {
+                    sections.push(a.cut(b, at[next_at]));
+                }
+                next_at += 1;
+            }
+        }
+        sections.extend(self.sections.last());
+        Strip { sections, closed: self.closed }
+    }
+
+    /// Repeat a closed strip once more, so slices can wrap around the seam.
+    /// `length` is the length of the loop, and `count` the number of locations in it.
+    pub fn unrolled(&self, length: f32, count: usize) -> Strip {
+        let mut sections = self.sections.clone();
+        sections.extend(self.sections.iter().skip(1).map(|s| Section {
+            distance: s.distance + length,
+            param: s.param + count as f32,
+            ..*s
+        }));
+        Strip { sections, closed: false }
+    }
+}
+
+/// What is needed to calculate the vertex attributes of a line
+pub(crate) struct VertexStyle<'a> {
+    pub color: &'a LineColor,
+    pub uv_mode: UvMode,
+    /// Total length of the line
+    pub length: f32,
+    /// Widest width of the line
+    pub width: f32,
+    /// Width of the fringe around the strips in world units, where the alpha fades to zero
+    pub feathering: f32,
+    pub border: Option<Border>,
+    pub bands: &'a [Band],
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Number of sections that went back along the line from the one before, counted for the mesh tests.
+    pub(crate) static BACKWARD_SECTIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Whether the sections are further back along the line than rounding errors can explain
+#[cfg(test)]
+fn goes_back(from: &Section, to: &Section) -> bool {
+    to.distance < from.distance - 1e-4 * from.distance.abs().max(1.)
+}
+
+/// Vertex buffers for a line mesh, built from strips.
+#[derive(Default)]
+pub(crate) struct MeshBuffers {
+    vertices: Vec<[f32; 3]>,
+    colors: Vec<[f32; 4]>,
+    uvs: Vec<[f32; 2]>,
+    /// Distance along the line, and position across it from -1 on the left side to 1 on the right side.
+    /// With feathering, -1 and 1 are at the outer edges of the fringe, so the shader smooths the edges there.
+    line_positions: Vec<[f32; 2]>,
+    indices: Vec<u32>,
+}
+
+/// A vertex across a section
+struct Column {
+    /// Position across the section, from -1 on the left side to 1 on the right side
+    across: f32,
+    /// How far beyond the side the vertex is, in the fringe
+    beyond: f32,
+    color: [f32; 4],
+    /// Whether the column is joined to the one before it.
+    /// Bands of different colors are not joined, so the color changes sharply between them.
+    joined: bool,
+}
+
+impl VertexStyle<'_> {
+    /// The vertices across the section, from the left side to the right side.
+    /// With `only`, just the vertices of that band are made, in its own color.
+    fn columns(&self, section: &Section, opacity: f32, only: Option<usize>) -> Vec<Column> {
+        let with_opacity = |[r, g, b, a]: [f32; 4]| [r, g, b, a * opacity];
+        let solid = |color: Color| {
+            let color = color.to_srgba();
+            with_opacity([color.red, color.green, color.blue, color.alpha])
+        };
+        let line_color = |across: f32| {
+            let distance = section.distance_at(section.point(across));
+            with_opacity(self.color.get(section.param, distance, self.length, across))
+        };
+
+        // The border is the same width in world units, wherever the width of the line changes.
+        // The line fits its bands and colors between the borders.
+        let inner = self.border.map_or(1., |border| 1. - (border.width / section.half_width).min(1.));
+
+        // Vertices across part of the width, at the middle, where caps fold around it, and at the stops of the line color
+        let stops = self.color.stops_across();
+        let between = |from: f32, to: f32, line_colored: bool| {
+            let mut across = vec![from];
+            across.extend(stops.iter().copied().filter(|stop| line_colored && *stop > from && *stop < to));
+            if from < 0. && to > 0. {
+                across.push(0.);
+            }
+            across.push(to);
+            across.sort_by(f32::total_cmp);
+            across.dedup();
+            across
+        };
+
+        let mut columns = Vec::new();
+        let mut add_band = |points: &[(f32, [f32; 4])]| {
+            for (i, (across, color)) in points.iter().enumerate() {
+                columns.push(Column { across: *across, beyond: 0., color: *color, joined: i > 0 });
+            }
+        };
+
+        let border_color = self.border.map(|border| solid(border.color));
+        if let (Some(color), None) = (border_color, only) {
+            add_band(&[(-1., color), (-inner, color)]);
+        }
+        let total: f32 = self.bands.iter().map(|band| band.fraction).sum();
+        if total > 0. {
+            let mut from = -1.;
+            for (index, band) in self.bands.iter().enumerate() {
+                let to = if index == self.bands.len() - 1 { 1. } else { from + 2. * band.fraction / total };
+                if only.map_or(true, |only| only == index) {
+                    // Dashes are added on top afterwards, with the line showing in the gaps
+                    let own_color = band.color.filter(|_| only.is_some() || band.dash_pattern.is_none());
+                    let points: Vec<_> = between(from, to, own_color.is_none()).into_iter()
+                        .map(|across| (across * inner, own_color.map_or_else(|| line_color(across), solid)))
+                        .collect();
+                    add_band(&points);
+                }
+                from = to;
+            }
+        } else if only.is_none() {
+            add_band(&between(-1., 1., true).into_iter().map(|across| (across * inner, line_color(across))).collect::<Vec<_>>());
+        }
+        if let (Some(color), None) = (border_color, only) {
+            add_band(&[(inner, color), (1., color)]);
+        }
+
+        if self.feathering > 0. && only.is_none() {
+            // The fringe fades out from the color at the sides
+            let fringe = |side: &Column| Column {
+                beyond: self.feathering,
+                color: [side.color[0], side.color[1], side.color[2], 0.],
+                joined: true,
+                ..*side
+            };
+            let (left, right) = (fringe(&columns[0]), fringe(&columns[columns.len() - 1]));
+            columns.insert(0, left);
+            columns.push(right);
+        }
+        if let Some(first) = columns.first_mut() {
+            first.joined = false;
+        }
+        columns
+    }
+}
+
+impl MeshBuffers {
+    pub fn add_strip(&mut self, strip: &Strip, style: &VertexStyle) {
+        // Colors blend linearly between the sections, so gradients need sections at their stops
+        let (Some(start), Some(end)) = (strip.sections.first(), strip.sections.last()) else {
+            return;
+        };
+        let stops = style.color.stops_between(start.distance, end.distance, style.length);
+        let subdivided;
+        let strip = if stops.is_empty() {
+            strip
+        } else {
+            subdivided = strip.subdivided(&stops);
+            &subdivided
+        };
+
+        // The sections, with the opacity of their vertices.
+        // Ends without a cap fade out in an extra section beyond them.
+        let mut rows: Vec<(Section, f32)> = strip.sections.iter().map(|section| (*section, 1.)).collect();
+        if style.feathering > 0. && !strip.closed {
+            if let Some(start) = strip.sections.first().filter(|section| !section.folded) {
+                rows.insert(0, (start.moved(-start.tangent * style.feathering), 0.));
+            }
+            if let Some(end) = strip.sections.last().filter(|section| !section.folded) {
+                rows.push((end.moved(end.tangent * style.feathering), 0.));
+            }
+        }
+        self.add_rows(&rows, style, None);
+
+        // Dashed bands, on top of the line
+        for (index, band) in style.bands.iter().enumerate() {
+            let Some(pattern) = &band.dash_pattern else {
+                continue;
+            };
+            for (from, to) in pattern.ranges(start.distance, end.distance) {
+                let dash: Vec<(Section, f32)> = strip.slice(from, to).sections.iter().map(|section| (*section, 1.)).collect();
+                self.add_rows(&dash, style, Some(index));
+            }
+        }
+    }
+
+    /// Add the inside of the outline, without its holes.
+    /// The texture is stretched over the bounds of the fill.
+    pub fn add_fill(&mut self, outline: &[Vec2], fill: &Fill) {
+        let (points, triangles) = fill.triangulate(outline);
+        let first = self.vertices.len() as u32;
+        let min = points.iter().fold(Vec2::MAX, |a, b| a.min(*b));
+        let size = (points.iter().fold(Vec2::MIN, |a, b| a.max(*b)) - min).max(Vec2::splat(f32::EPSILON));
+        for point in &points {
+            self.vertices.push([point.x, point.y, 0.]);
+            let uv = (*point - min) / size;
+            self.uvs.push([uv.x, 1. - uv.y]);
+            self.line_positions.push([0., FILL_ACROSS]);
+        }
+        self.colors.extend(fill.colors(&points));
+        for [a, b, c] in triangles {
+            self.add_triangle(first + a as u32, first + b as u32, first + c as u32);
+        }
+    }
+
+    /// Add a vertex for each column of each section, and join them with quads
+    fn add_rows(&mut self, rows: &[(Section, f32)], style: &VertexStyle, only: Option<usize>) {
+        let first = self.vertices.len() as u32;
+
+        // Every section has the same columns, so they are joined the same way
+        let mut joined = Vec::new();
+        for (section, opacity) in rows {
+            let columns = style.columns(section, *opacity, only);
+            for column in &columns {
+                let point = if column.beyond > 0. {
+                    section.beyond(column.across, column.beyond)
+                } else {
+                    section.point(column.across)
+                };
+                self.vertices.push([point.x, point.y, 0.]);
+                self.colors.push(column.color);
+                let distance = section.distance_at(point);
+                self.uvs.push([style.uv_mode.u(distance, style.length, style.width), (column.across + 1.) / 2.]);
+                let across = if style.feathering > 0. {
+                    (column.across * section.half_width + column.beyond.copysign(column.across)) / (section.half_width + style.feathering)
+                } else {
+                    column.across
+                };
+                self.line_positions.push([distance, across]);
+            }
+            joined = columns.iter().map(|column| column.joined).collect();
+        }
+
+        #[cfg(test)]
+        BACKWARD_SECTIONS.with(|count| count.set(count.get() + rows.windows(2).filter(|pair| goes_back(&pair[0].0, &pair[1].0)).count()));
+
+        let columns = joined.len() as u32;
+        for i in 1..rows.len() as u32 {
+            let prev = first + (i - 1) * columns;
+            let next = prev + columns;
+            for j in 1..columns {
+                if joined[j as usize] {
+                    self.add_quad(prev + j - 1, prev + j, next + j - 1, next + j);
+                }
+            }
+        }
+    }
+
+    /// Add 2 triangles for the quad, where a-b is the previous edge and c-d is the next.
+    fn add_quad(&mut self, a: u32, b: u32, c: u32, d: u32) {
+        self.add_triangle(a, b, c);
+        self.add_triangle(b, d, c);
+    }
+
+    /// Add a triangle, unless it has no area. Those appear where the sides of the strip meet.
+    /// Triangles are turned counterclockwise, as they are flipped where the strip folds over itself.
+    fn add_triangle(&mut self, a: u32, b: u32, c: u32) {
+        let [pa, pb, pc] = [a, b, c].map(|i| Vec3::from(self.vertices[i as usize]).truncate());
+        let longest = pa.distance_squared(pb).max(pb.distance_squared(pc)).max(pc.distance_squared(pa));
+        let cross = (pb - pa).perp_dot(pc - pa);
+        if cross.abs() <= 1e-5 * longest {
+            return;
+        }
+        if cross > 0. {
+            self.indices.extend([a, b, c]);
+        } else {
+            self.indices.extend([a, c, b]);
+        }
+    }
+
+    /// Replace the triangles with pieces that do not overlap, so each point is covered once.
+    /// Where triangles overlapped, the attributes of the last one are used.
+    pub fn merge_overlaps(&mut self) {
+        let position = |i: u32| Vec3::from(self.vertices[i as usize]).truncate();
+        let triangles: Vec<[u32; 3]> = self.indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect();
+        let pieces = sweep::union(&triangles.iter().map(|t| t.map(position)).collect::<Vec<_>>());
+
+        let old = std::mem::take(self);
+        for (index, piece) in pieces {
+            let corners = triangles[index].map(|i| i as usize);
+            let [pa, pb, pc] = corners.map(|i| Vec3::from(old.vertices[i]).truncate());
+            let area = (pb - pa).perp_dot(pc - pa);
+            let first = self.vertices.len() as u32;
+            for point in piece {
+                // Barycentric coordinates of the point in the triangle it came from
+                let wb = (point - pa).perp_dot(pc - pa) / area;
+                let wc = (pb - pa).perp_dot(point - pa) / area;
+                let weights = [1. - wb - wc, wb, wc];
+                self.vertices.push([point.x, point.y, 0.]);
+                self.colors.push(interpolate(&old.colors, corners, weights));
+                self.uvs.push(interpolate(&old.uvs, corners, weights));
+                self.line_positions.push(interpolate(&old.line_positions, corners, weights));
+            }
+            self.add_triangle(first, first + 1, first + 2);
+        }
+    }
+
+    pub fn into_mesh(self) -> Mesh {
+        Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.vertices)
+            .with_inserted_indices(mesh::Indices::U32(self.indices))
+            .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, self.colors)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs)
+            .with_inserted_attribute(ATTRIBUTE_LINE_POSITION, self.line_positions)
+    }
+}
+
+/// Weighted sum of the values at the given indices
+fn interpolate<const N: usize>(values: &[[f32; N]], indices: [usize; 3], weights: [f32; 3]) -> [f32; N] {
+    std::array::from_fn(|k| (0..3).map(|i| values[indices[i]][k] * weights[i]).sum())
+}
@@ -0,0 +1,507 @@
+use std::{f32::consts::PI, fmt};
+
+use bevy::prelude::*;
+
+use crate::{
+    flex_line::{CapStyle, CornerStyle, FlexLine, LineColor},
+    path::{LinePath, PathSegment},
+};
+
+/// Why SVG path data could not be read
+#[derive(Clone, Debug, PartialEq)]
+pub enum SvgError {
+    /// A character that is not part of path data, at the given byte position
+    UnexpectedCharacter(usize),
+    /// A command is missing some of its numbers
+    MissingNumber(usize),
+    /// The path data does not start with a move command
+    MissingMoveTo,
+}
+
+impl fmt::Display for SvgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SvgError::UnexpectedCharacter(position) => write!(f, "unexpected character at {position} in path data"),
+            SvgError::MissingNumber(position) => write!(f, "expected a number at {position} in path data"),
+            SvgError::MissingMoveTo => write!(f, "path data must start with a move command"),
+        }
+    }
+}
+
+impl std::error::Error for SvgError {}
+
+/// Stroke attributes of an SVG element, which are all optional.
+#[derive(Clone, Default)]
+pub struct SvgStyle {
+    /// From `stroke` and `stroke-opacity`
+    pub stroke: Option<Color>,
+    /// From `stroke-width`
+    pub stroke_width: Option<f32>,
+    /// From `stroke-linejoin` and `stroke-miterlimit`
+    pub line_join: Option<CornerStyle>,
+    /// From `stroke-linecap`
+    pub line_cap: Option<CapStyle>,
+}
+
+/// Number of vertices in a circle, for round joins and caps
+const ROUND_RESOLUTION: usize = 16;
+
+impl SvgStyle {
+    /// Read the stroke attributes from name and value pairs.
+    /// Declarations in a `style` attribute are read as well, and take precedence like in SVG.
+    pub fn from_attributes<'a>(attributes: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        let mut properties = Vec::new();
+        let mut declarations = Vec::new();
+        for (name, value) in attributes {
+            if name.trim() == "style" {
+                declarations.extend(value.split(';').filter_map(|declaration| declaration.split_once(':')));
+            } else {
+                properties.push((name, value));
+            }
+        }
+        properties.extend(declarations);
+
+        let mut style = SvgStyle::default();
+        let mut opacity = 1.;
+        let mut miter_limit = 4.;
+        let mut join = None;
+        for (name, value) in properties {
+            let value = value.trim();
+            match name.trim() {
+                "stroke" => style.stroke = parse_color(value),
+                "stroke-opacity" => opacity = value.parse().unwrap_or(opacity),
+                "stroke-width" => style.stroke_width = value.trim_end_matches("px").parse().ok(),
+                // Limits below 1 are an error in SVG, and are ignored like other invalid values
+                "stroke-miterlimit" => miter_limit = value.parse().ok().filter(|limit| *limit >= 1.).unwrap_or(miter_limit),
+                "stroke-linejoin" => join = Some(value),
+                "stroke-linecap" => style.line_cap = match value {
+                    "butt" => Some(CapStyle::Butt),
+                    "round" => Some(CapStyle::Round { resolution: ROUND_RESOLUTION }),
+                    "square" => Some(CapStyle::Square),
+                    _ => None,
+                },
+                _ => {},
+            }
+        }
+
+        style.stroke = style.stroke.map(|color| color.with_alpha(color.alpha() * opacity));
+        style.line_join = match join {
+            Some("miter") | Some("miter-clip") | Some("arcs") => Some(CornerStyle::Miter { limit: miter_limit }),
+            Some("round") => Some(CornerStyle::Round { resolution: ROUND_RESOLUTION }),
+            Some("bevel") => Some(CornerStyle::Bevel),
+            _ => None,
+        };
+        style
+    }
+
+    /// Set the attributes that are present on the line
+    pub fn apply(&self, line: &mut FlexLine) {
+        if let Some(color) = self.stroke {
+            line.color = LineColor::Fill(color);
+        }
+        if let Some(width) = self.stroke_width {
+            line.width = width;
+        }
+        if let Some(corner_style) = self.line_join {
+            line.corner_style = corner_style;
+        }
+        if let Some(cap) = &self.line_cap {
+            line.start_cap = cap.clone();
+            line.end_cap = cap.clone();
+        }
+    }
+}
+
+impl FlexLine {
+    /// Make a line for each subpath of SVG path data, styled by `template` and then `style`.
+    /// Curves are flattened with the given tolerance, and closed subpaths become connected lines.
+    /// Coordinates are kept as they are, so the Y axis points down like in SVG.
+    pub fn from_svg(d: &str, style: &SvgStyle, tolerance: f32, template: &FlexLine) -> Result<Vec<FlexLine>, SvgError> {
+        let mut template = template.clone();
+        style.apply(&mut template);
+        Ok(LinePath::from_svg(d)?.to_lines(tolerance, &template))
+    }
+}
+
+impl LinePath {
+    /// Read SVG path data, as in the `d` attribute of a `<path>`.
+    /// All commands are supported, both absolute and relative. Arcs are converted to cubic curves.
+    pub fn from_svg(d: &str) -> Result<LinePath, SvgError> {
+        let mut parser = Parser { data: d.as_bytes(), position: 0 };
+        let mut path = LinePath::new();
+        let mut current = Vec2::ZERO;
+        let mut start = Vec2::ZERO;
+        // Control point of the previous curve, to be reflected by the smooth commands
+        let mut last_cubic: Option<Vec2> = None;
+        let mut last_quadratic: Option<Vec2> = None;
+
+        let mut command = match parser.command() {
+            Some(letter @ (b'M' | b'm')) => Some(letter),
+            None if parser.at_end() => return Ok(path),
+            _ => return Err(SvgError::MissingMoveTo),
+        };
+        while let Some(letter) = command.or_else(|| parser.command()) {
+            command = None;
+            let relative = letter.is_ascii_lowercase();
+            let origin = |current: Vec2| if relative { current } else { Vec2::ZERO };
+            let (mut cubic, mut quadratic) = (None, None);
+
+            match letter.to_ascii_uppercase() {
+                b'M' => {
+                    current = origin(current) + parser.point()?;
+                    start = current;
+                    path.segments.push(PathSegment::MoveTo(current));
+                    // Further points are implicit line commands
+                    while parser.has_number() {
+                        current = origin(current) + parser.point()?;
+                        path.segments.push(PathSegment::LineTo(current));
+                    }
+                },
+                b'L' => loop {
+                    current = origin(current) + parser.point()?;
+                    path.segments.push(PathSegment::LineTo(current));
+                    if !parser.has_number() {
+                        break;
+                    }
+                },
+                b'H' => loop {
+                    current.x = origin(current).x + parser.number()?;
+                    path.segments.push(PathSegment::LineTo(current));
+                    if !parser.has_number() {
+                        break;
+                    }
+                },
+                b'V' => loop {
+                    current.y = origin(current).y + parser.number()?;
+                    path.segments.push(PathSegment::LineTo(current));
+                    if !parser.has_number() {
+                        break;
+                    }
+                },
+                b'C' | b'S' => loop {
+                    let control1 = if letter.eq_ignore_ascii_case(&b'C') {
+                        origin(current) + parser.point()?
+                    } else {
+                        cubic.or(last_cubic).map_or(current, |control| 2. * current - control)
+                    };
+                    let control2 = origin(current) + parser.point()?;
+                    let to = origin(current) + parser.point()?;
+                    path.segments.push(PathSegment::CubicTo { control1, control2, to });
+                    cubic = Some(control2);
+                    current = to;
+                    if !parser.has_number() {
+                        break;
+                    }
+                },
+                b'Q' | b'T' => loop {
+                    let control = if letter.eq_ignore_ascii_case(&b'Q') {
+                        origin(current) + parser.point()?
+                    } else {
+                        quadratic.or(last_quadratic).map_or(current, |control| 2. * current - control)
+                    };
+                    let to = origin(current) + parser.point()?;
+                    path.segments.push(PathSegment::QuadraticTo { control, to });
+                    quadratic = Some(control);
+                    current = to;
+                    if !parser.has_number() {
+                        break;
+                    }
+                },
+                b'A' => loop {
+                    let radii = Vec2::new(parser.number()?, parser.number()?);
+                    let rotation = parser.number()?;
+                    let large_arc = parser.flag()?;
+                    let sweep = parser.flag()?;
+                    let to = origin(current) + parser.point()?;
+                    path.segments.extend(arc_segments(current, radii, rotation, large_arc, sweep, to));
+                    current = to;
+                    if !parser.has_number() {
+                        break;
+                    }
+                },
+                b'Z' => {
+                    path.segments.push(PathSegment::Close);
+                    current = start;
+                },
+                _ => return Err(SvgError::UnexpectedCharacter(parser.position - 1)),
+            }
+
+            last_cubic = cubic;
+            last_quadratic = quadratic;
+        }
+
+        if !parser.at_end() {
+            return Err(SvgError::UnexpectedCharacter(parser.position));
+        }
+        Ok(path)
+    }
+}
+
+/// Reads the tokens of path data
+struct Parser<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl Parser<'_> {
+    /// Skip whitespace, and a comma if `comma` is set
+    fn skip_separators(&mut self, comma: bool) {
+        let mut comma = comma;
+        while let Some(&byte) = self.data.get(self.position) {
+            if byte.is_ascii_whitespace() {
+                self.position += 1;
+            } else if byte == b',' && comma {
+                comma = false;
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_separators(false);
+        self.position >= self.data.len()
+    }
+
+    /// The next command letter, if there is one
+    fn command(&mut self) -> Option<u8> {
+        self.skip_separators(false);
+        let byte = *self.data.get(self.position)?;
+        if byte.is_ascii_alphabetic() {
+            self.position += 1;
+            Some(byte)
+        } else {
+            None
+        }
+    }
+
+    fn has_number(&mut self) -> bool {
+        self.skip_separators(true);
+        matches!(self.data.get(self.position), Some(b'0'..=b'9' | b'-' | b'+' | b'.'))
+    }
+
+    fn number(&mut self) -> Result<f32, SvgError> {
+        self.skip_separators(true);
+        let begin = self.position;
+        let digits = |parser: &mut Self| {
+            let from = parser.position;
+            while parser.data.get(parser.position).is_some_and(u8::is_ascii_digit) {
+                parser.position += 1;
+            }
+            parser.position > from
+        };
+
+        if matches!(self.data.get(self.position), Some(b'-' | b'+')) {
+            self.position += 1;
+        }
+        let mut valid = digits(self);
+        if self.data.get(self.position) == Some(&b'.') {
+            self.position += 1;
+            valid |= digits(self);
+        }
+        if valid && matches!(self.data.get(self.position), Some(b'e' | b'E')) {
+            // Only an exponent if digits follow, as in "1e5", not the start of another token
+            let mantissa_end = self.position;
+            self.position += 1;
+            if matches!(self.data.get(self.position), Some(b'-' | b'+')) {
+                self.position += 1;
+            }
+            if !digits(self) {
+                self.position = mantissa_end;
+            }
+        }
+
+        let text = std::str::from_utf8(&self.data[begin..self.position]).unwrap_or_default();
+        match text.parse() {
+            Ok(number) if valid => Ok(number),
+            _ => Err(SvgError::MissingNumber(begin)),
+        }
+    }
+
+    fn point(&mut self) -> Result<Vec2, SvgError> {
+        Ok(Vec2::new(self.number()?, self.number()?))
+    }
+
+    /// Arc flags are single digits, which need no separator after them
+    fn flag(&mut self) -> Result<bool, SvgError> {
+        self.skip_separators(true);
+        let flag = match self.data.get(self.position) {
+            Some(b'0') => false,
+            Some(b'1') => true,
+            _ => return Err(SvgError::MissingNumber(self.position)),
+        };
+        self.position += 1;
+        Ok(flag)
+    }
+}
+
+/// Cubic curves for an elliptical arc, given the SVG way with its end points.
+/// Each curve covers at most a quarter of the ellipse.
+fn arc_segments(from: Vec2, radii: Vec2, rotation: f32, large_arc: bool, sweep: bool, to: Vec2) -> Vec<PathSegment> {
+    if from == to {
+        return Vec::new();
+    }
+    let mut radii = radii.abs();
+    if radii.x == 0. || radii.y == 0. {
+        return vec![PathSegment::LineTo(to)];
+    }
+
+    // Find the center, in coordinates rotated with the ellipse
+    let angle = rotation.to_radians();
+    let rotation = Vec2::from_angle(angle);
+    let half = Vec2::from_angle(-angle).rotate((from - to) / 2.);
+    let scale = (half / radii).length_squared();
+    if scale > 1. {
+        radii *= scale.sqrt();
+    }
+    let (rx2, ry2) = (radii.x * radii.x, radii.y * radii.y);
+    let (hx2, hy2) = (half.x * half.x, half.y * half.y);
+    let sign = if large_arc == sweep { -1. } else { 1. };
+    let coefficient = sign * ((rx2 * ry2 - rx2 * hy2 - ry2 * hx2) / (rx2 * hy2 + ry2 * hx2)).max(0.).sqrt();
+    let center = coefficient * Vec2::new(radii.x * half.y / radii.y, -radii.y * half.x / radii.x);
+
+    let start_angle = Vec2::X.angle_between((half - center) / radii);
+    let mut sweep_angle = ((half - center) / radii).angle_between((-half - center) / radii);
+    if !sweep && sweep_angle > 0. {
+        sweep_angle -= 2. * PI;
+    } else if sweep && sweep_angle < 0. {
+        sweep_angle += 2. * PI;
+    }
+
+    let center = rotation.rotate(center) + (from + to) / 2.;
+    let point = |angle: f32| center + rotation.rotate(radii * Vec2::from_angle(angle));
+    let derivative = |angle: f32| rotation.rotate(radii * Vec2::new(-angle.sin(), angle.cos()));
+
+    let count = (sweep_angle.abs() / (PI / 2.) - 1e-4).ceil().max(1.) as usize;
+    let step = sweep_angle / count as f32;
+    let handle = 4. / 3. * (step / 4.).tan();
+    (0..count).map(|i| {
+        let (a0, a1) = (start_angle + step * i as f32, start_angle + step * (i + 1) as f32);
+        PathSegment::CubicTo {
+            control1: point(a0) + derivative(a0) * handle,
+            control2: point(a1) - derivative(a1) * handle,
+            to: if i + 1 == count { to } else { point(a1) },
+        }
+    }).collect()
+}
+
+/// Color of an SVG paint, if it is a plain color
+fn parse_color(value: &str) -> Option<Color> {
+    let value = value.trim().to_ascii_lowercase();
+    if value == "none" || value == "transparent" {
+        return Some(Color::NONE);
+    }
+    if let Some(hex) = value.strip_prefix('#') {
+        return Srgba::hex(hex).ok().map(Color::Srgba);
+    }
+    if let Some(arguments) = value.strip_prefix("rgb(").or_else(|| value.strip_prefix("rgba(")) {
+        // Channels go to 255 and alpha to 1, unless they are percentages
+        let channel = |text: &str, full: f32| {
+            let text = text.trim();
+            match text.strip_suffix('%') {
+                Some(percentage) => percentage.parse::<f32>().ok().map(|p| p / 100.),
+                None => text.parse::<f32>().ok().map(|c| c / full),
+            }
+        };
+        let arguments: Vec<&str> = arguments.trim_end_matches(')').split(',').collect();
+        let [r, g, b] = [0, 1, 2].map(|i| arguments.get(i).and_then(|text| channel(text, 255.)));
+        let alpha = match arguments.get(3) {
+            Some(text) => channel(text, 1.)?,
+            None => 1.,
+        };
+        return Some(Color::srgba(r?, g?, b?, alpha));
+    }
+
+    let [r, g, b] = match value.as_str() {
+        "black" => [0, 0, 0],
+        "white" => [255, 255, 255],
+        "red" => [255, 0, 0],
+        "lime" => [0, 255, 0],
+        "green" => [0, 128, 0],
+        "blue" => [0, 0, 255],
+        "yellow" => [255, 255, 0],
+        "cyan" | "aqua" => [0, 255, 255],
+        "magenta" | "fuchsia" => [255, 0, 255],
+        "gray" | "grey" => [128, 128, 128],
+        "silver" => [192, 192, 192],
+        "maroon" => [128, 0, 0],
+        "olive" => [128, 128, 0],
+        "navy" => [0, 0, 128],
+        "purple" => [128, 0, 128],
+        "teal" => [0, 128, 128],
+        "orange" => [255, 165, 0],
+        _ => return None,
+    };
+    Some(Color::srgb_u8(r, g, b))
+}
+
+#[test]
+fn test_parse_path_commands() {
+    let path = LinePath::from_svg("M10,10 h10 v10 H10 z m5-5 l1 1 2 2 Q0 0 1 1 T 3 3").unwrap();
+    assert_eq!(path.segments, vec![
+        PathSegment::MoveTo(Vec2::new(10., 10.)),
+        PathSegment::LineTo(Vec2::new(20., 10.)),
+        PathSegment::LineTo(Vec2::new(20., 20.)),
+        PathSegment::LineTo(Vec2::new(10., 20.)),
+        PathSegment::Close,
+        PathSegment::MoveTo(Vec2::new(15., 5.)),
+        PathSegment::LineTo(Vec2::new(16., 6.)),
+        PathSegment::LineTo(Vec2::new(18., 8.)),
+        PathSegment::QuadraticTo { control: Vec2::new(0., 0.), to: Vec2::new(1., 1.) },
+        PathSegment::QuadraticTo { control: Vec2::new(2., 2.), to: Vec2::new(3., 3.) },
+    ]);
+
+    assert_eq!(LinePath::from_svg("M0 0 L 1").unwrap_err(), SvgError::MissingNumber(8));
+    assert_eq!(LinePath::from_svg("L0 0").unwrap_err(), SvgError::MissingMoveTo);
+    assert_eq!(LinePath::from_svg("M0 0 X").unwrap_err(), SvgError::UnexpectedCharacter(5));
+}
+
+#[test]
+fn test_parse_compact_numbers() {
+    let path = LinePath::from_svg("M.5.5-1e1-2E1c1,1,2,2,3,3s1 1 2 2a5 5 0 1015 0").unwrap();
+    assert_eq!(path.segments[0], PathSegment::MoveTo(Vec2::new(0.5, 0.5)));
+    assert_eq!(path.segments[1], PathSegment::LineTo(Vec2::new(-10., -20.)));
+    assert_eq!(path.segments[3], PathSegment::CubicTo {
+        control1: Vec2::new(-6., -16.),
+        control2: Vec2::new(-6., -16.),
+        to: Vec2::new(-5., -15.),
+    });
+    let Some(PathSegment::CubicTo { to, .. }) = path.segments.last() else {
+        panic!("The arc should end with a curve");
+    };
+    assert_eq!(*to, Vec2::new(10., -15.));
+}
+
+#[test]
+fn test_parse_arc() {
+    // Half circle with radius 10 from (0, 0) to (20, 0), bulging towards negative y
+    let path = LinePath::from_svg("M0 0 A10 10 0 0 1 20 0").unwrap();
+    let points = &path.flatten(0.01)[0].points;
+    for point in points {
+        assert!((point.distance(Vec2::new(10., 0.)) - 10.).abs() < 0.02);
+        assert!(point.y <= 1e-4);
+    }
+}
+
+#[test]
+fn test_style_attributes() {
+    let style = SvgStyle::from_attributes([
+        ("stroke", "#ff0000"),
+        ("stroke-width", "3"),
+        ("style", "stroke-linejoin: bevel; stroke-width: 4px"),
+    ]);
+    assert_eq!(style.stroke, Some(Color::srgb(1., 0., 0.)));
+    assert_eq!(style.stroke_width, Some(4.));
+    assert!(matches!(style.line_join, Some(CornerStyle::Bevel)));
+
+    let lines = FlexLine::from_svg("M0 0 L10 0 L10 10 Z M20 0 L30 0", &style, 0.1, &FlexLine::default()).unwrap();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].connection_style == crate::ConnectionStyle::Connected);
+    assert!(lines[1].connection_style == crate::ConnectionStyle::Unconnected);
+    assert_eq!(lines[1].width, 4.);
+
+    // Miter limits below 1 are invalid, and the default is kept
+    let style = SvgStyle::from_attributes([("stroke-linejoin", "miter"), ("stroke-miterlimit", "0.5")]);
+    assert!(matches!(style.line_join, Some(CornerStyle::Miter { limit }) if limit == 4.));
+}
@@ -0,0 +1,302 @@
+use std::fmt::Write;
+
+use bevy::{math::{Affine2, Affine3A}, prelude::*};
+
+use crate::{error::FlexLineError, fill::FillRule, flex_line::{CapStyle, CornerStyle, FlexLine}};
+
+/// What is written for each line when exporting to SVG
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SvgExportMode {
+    /// The path through the locations, with stroke attributes.
+    /// Styles SVG does not have, like varying widths, markers and alignment, are left out.
+    /// Start and end caps that differ are drawn as shapes of their own, and dashes then end flat.
+    Path,
+    /// The triangles of the mesh, each filled with the average color of its vertices
+    Triangles,
+}
+
+/// An exported line, and the area it covers
+struct SvgElement {
+    markup: String,
+    min: Vec2,
+    max: Vec2,
+}
+
+impl FlexLine {
+    /// An SVG document with the line. The Y axis points up, like in Bevy.
+    pub fn to_svg(&self, mode: SvgExportMode) -> Result<String, FlexLineError> {
+        self.validate()?;
+        Ok(svg_document(&[self.svg_element(mode, Affine2::IDENTITY)]))
+    }
+
+    fn svg_element(&self, mode: SvgExportMode, transform: Affine2) -> SvgElement {
+        let mut markup = String::new();
+        let points: Vec<Vec2>;
+        let mut margin = 0.;
+
+        match mode {
+            SvgExportMode::Path => {
+                let line = self.resolved();
+                points = line.locations.iter().map(|location| transform.transform_point2(*location)).collect();
+                let scale = transform.matrix2.determinant().abs().sqrt();
+                let width = self.width * scale;
+                // Leave room for joins and caps
+                margin = line.widths.iter().flatten().fold(self.width, |a, b| a.max(*b)) * scale;
+
+                let d = path_data(&points, self.is_connected());
+
+                if let (Some(fill), true) = (&self.fill, self.is_connected()) {
+                    // The fill has the holes, which the line does not outline
+                    let mut fill_d = d.clone();
+                    for hole in &fill.holes {
+                        fill_d.push(' ');
+                        fill_d.push_str(&path_data(&hole.iter().map(|point| transform.transform_point2(*point)).collect::<Vec<_>>(), true));
+                    }
+                    let rule = match fill.rule {
+                        FillRule::NonZero => "nonzero",
+                        FillRule::EvenOdd => "evenodd",
+                    };
+                    let color = average_color(fill.colors(&line.locations).into_iter());
+                    let _ = write!(markup, r#"<path d="{fill_d}" {} fill-rule="{rule}"/>"#, paint("fill", color));
+                }
+
+                let distances = line.distances();
+                let length = line.length(&distances);
+                let color = average_color(distances.iter().enumerate().map(|(i, distance)| line.color.get(i as f32, *distance, length, 0.)));
+                let _ = write!(markup, r#"<path d="{d}" fill="none" {} stroke-width="{width}""#, paint("stroke", color));
+
+                let (join, miter_limit) = match self.corner_style {
+                    CornerStyle::Sharp => ("miter", None),
+                    CornerStyle::Miter { limit } => ("miter", Some(limit)),
+                    CornerStyle::Bevel => ("bevel", None),
+                    CornerStyle::Rounded { .. } | CornerStyle::Round { .. } => ("round", None),
+                };
+                let _ = write!(markup, r#" stroke-linejoin="{join}""#);
+                // Without a limit, the miter is never cut off
+                let _ = write!(markup, r#" stroke-miterlimit="{}""#, miter_limit.unwrap_or(1e6));
+
+                let mut caps = String::new();
+                if !self.is_connected() {
+                    let svg_cap = |cap: &CapStyle| match cap {
+                        CapStyle::Square => "square",
+                        CapStyle::Round { .. } => "round",
+                        _ => "butt",
+                    };
+                    if svg_cap(&self.start_cap) == svg_cap(&self.end_cap) {
+                        let _ = write!(markup, r#" stroke-linecap="{}""#, svg_cap(&self.start_cap));
+                    } else if points.len() >= 2 {
+                        // SVG has one cap for both ends, so the caps are drawn as shapes of their own
+                        markup.push_str(r#" stroke-linecap="butt""#);
+                        let last = points.len() - 1;
+                        for (cap, end, before) in [(&self.start_cap, points[0], points[1]), (&self.end_cap, points[last], points[last - 1])] {
+                            let forward = (end - before).normalize_or_zero() * width / 2.;
+                            let outline: Vec<Vec2> = cap.outline().into_iter().map(|p| end + forward * p.x + forward.perp() * p.y).collect();
+                            if forward != Vec2::ZERO && !outline.is_empty() {
+                                let _ = write!(caps, r#"<path d="{}" {}/>"#, path_data(&outline, true), paint("fill", color));
+                            }
+                        }
+                    }
+                }
+                if let Some(pattern) = &self.dash_pattern {
+                    let lengths: Vec<String> = pattern.lengths.iter().map(|length| (length * scale).to_string()).collect();
+                    let _ = write!(markup, r#" stroke-dasharray="{}" stroke-dashoffset="{}""#, lengths.join(" "), pattern.offset * scale);
+                }
+                markup.push_str("/>");
+                markup.push_str(&caps);
+            },
+            SvgExportMode::Triangles => {
+                let mesh = self.make_mesh();
+                let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION).and_then(|positions| positions.as_float3()).unwrap_or_default();
+                points = positions.iter().map(|p| transform.transform_point2(Vec2::new(p[0], p[1]))).collect();
+                let colors = match mesh.attribute(Mesh::ATTRIBUTE_COLOR) {
+                    Some(bevy::render::mesh::VertexAttributeValues::Float32x4(colors)) => colors.as_slice(),
+                    _ => &[],
+                };
+                let indices: Vec<usize> = mesh.indices().map(|indices| indices.iter().collect()).unwrap_or_default();
+
+                for triangle in indices.chunks_exact(3) {
+                    let color = average_color(triangle.iter().map(|i| colors.get(*i).copied().unwrap_or([1.; 4])));
+                    let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|i| points[i]);
+                    let _ = write!(markup, r#"<polygon points="{} {} {} {} {} {}" {}/>"#, a.x, a.y, b.x, b.y, c.x, c.y, paint("fill", color));
+                }
+            },
+        }
+
+        let min = points.iter().fold(Vec2::MAX, |a, b| a.min(*b)) - margin;
+        let max = points.iter().fold(Vec2::MIN, |a, b| a.max(*b)) + margin;
+        SvgElement { markup, min, max }
+    }
+}
+
+/// An SVG document with all lines in the world, placed by their `GlobalTransform`.
+/// Lines are drawn in order of their Z coordinate, and invalid lines are left out. No renderer is needed.
+pub fn world_to_svg(world: &mut World, mode: SvgExportMode) -> String {
+    let mut query = world.query::<(&FlexLine, Option<&GlobalTransform>)>();
+    let mut lines: Vec<(f32, SvgElement)> = query.iter(world).filter(|(line, _)| line.validate().is_ok()).map(|(line, transform)| {
+        let affine = transform.map_or(Affine3A::IDENTITY, |transform| transform.affine());
+        let transform = Affine2::from_cols(affine.x_axis.truncate(), affine.y_axis.truncate(), affine.translation.truncate());
+        (affine.translation.z, line.svg_element(mode, transform))
+    }).collect();
+    lines.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let elements: Vec<SvgElement> = lines.into_iter().map(|(_, element)| element).collect();
+    svg_document(&elements)
+}
+
+/// Wrap the elements in a document that fits them.
+/// The Y axis is flipped, as it points down in SVG.
+fn svg_document(elements: &[SvgElement]) -> String {
+    let min = elements.iter().fold(Vec2::MAX, |a, b| a.min(b.min));
+    let max = elements.iter().fold(Vec2::MIN, |a, b| a.max(b.max));
+    let (min, size) = if min.cmple(max).all() { (min, max - min) } else { (Vec2::ZERO, Vec2::ONE) };
+
+    let mut document = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}"><g transform="scale(1 -1)">"#,
+        min.x, -min.y - size.y, size.x, size.y,
+    );
+    for element in elements {
+        document.push_str(&element.markup);
+    }
+    document.push_str("</g></svg>");
+    document
+}
+
+/// Path data through the points
+fn path_data(points: &[Vec2], closed: bool) -> String {
+    let mut d = String::new();
+    for (i, point) in points.iter().enumerate() {
+        let _ = write!(d, "{}{} {} ", if i == 0 { "M" } else { "L" }, point.x, point.y);
+    }
+    if closed {
+        d.push('Z');
+    }
+    d.trim_end().to_string()
+}
+
+fn average_color(colors: impl Iterator<Item = [f32; 4]>) -> [f32; 4] {
+    let (sum, count) = colors.fold((Vec4::ZERO, 0), |(sum, count), color| (sum + Vec4::from(color), count + 1));
+    (sum / count.max(1) as f32).into()
+}
+
+/// Fill or stroke attributes for a color
+fn paint(attribute: &str, [r, g, b, a]: [f32; 4]) -> String {
+    let [r, g, b] = [r, g, b].map(|channel| (channel.clamp(0., 1.) * 255.).round() as u8);
+    let mut paint = format!(r#"{attribute}="rgb({r},{g},{b})""#);
+    if a < 1. {
+        let _ = write!(paint, r#" {attribute}-opacity="{}""#, a.max(0.));
+    }
+    paint
+}
+
+#[test]
+fn test_export_path() {
+    let line = FlexLine {
+        locations: vec![Vec2::new(0., 0.), Vec2::new(10., 0.), Vec2::new(10., 10.)],
+        width: 2.,
+        corner_style: CornerStyle::Bevel,
+        color: crate::LineColor::Fill(Color::srgba(1., 0., 0., 0.5)),
+        start_cap: CapStyle::Round { resolution: 16 },
+        end_cap: CapStyle::Round { resolution: 16 },
+        connection_style: crate::ConnectionStyle::Unconnected,
+        ..default()
+    };
+    let svg = line.to_svg(SvgExportMode::Path).unwrap();
+    assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="-2 -12 14 14">"#));
+    assert!(svg.contains(r#"<path d="M0 0 L10 0 L10 10" fill="none" stroke="rgb(255,0,0)" stroke-opacity="0.5" stroke-width="2""#));
+    assert!(svg.contains(r#"stroke-linejoin="bevel""#));
+    assert!(svg.contains(r#"stroke-linecap="round""#));
+}
+
+#[test]
+fn test_export_fill() {
+    let line = FlexLine {
+        locations: vec![Vec2::new(0., 0.), Vec2::new(10., 0.), Vec2::new(10., 10.)],
+        fill: Some(crate::Fill::gradient(Vec2::X, Color::BLACK, Color::WHITE).with_hole(vec![Vec2::new(8., 1.), Vec2::new(9., 1.), Vec2::new(9., 2.)]).with_rule(FillRule::EvenOdd)),
+        ..default()
+    };
+    let svg = line.to_svg(SvgExportMode::Path).unwrap();
+    assert!(svg.contains(r#"<path d="M0 0 L10 0 L10 10 Z M8 1 L9 1 L9 2 Z" fill="rgb(170,170,170)" fill-rule="evenodd"/>"#));
+    assert!(svg.contains(r#"<path d="M0 0 L10 0 L10 10 Z" fill="none" stroke="rgb(255,255,255)""#));
+}
+
+#[test]
+fn test_export_triangles() {
+    let line = FlexLine {
+        locations: vec![Vec2::new(0., 0.), Vec2::new(10., 0.)],
+        color: crate::LineColor::GradientAcross { left: Color::srgb(0.3, 0.3, 0.3), right: Color::srgb(0.9, 0.9, 0.9) },
+        connection_style: crate::ConnectionStyle::Unconnected,
+        ..default()
+    };
+    let svg = line.to_svg(SvgExportMode::Triangles).unwrap();
+    assert_eq!(svg.matches("<polygon").count(), 4);
+    // Each triangle has a different mix of vertices from the sides and the middle
+    let mut fills: Vec<&str> = svg.split("fill=").skip(1).collect();
+    fills.sort();
+    fills.dedup();
+    assert_eq!(fills.len(), 4);
+    assert!(svg.contains(r#"fill="rgb(102,102,102)""#) && svg.contains(r#"fill="rgb(204,204,204)""#));
+}
+
+#[test]
+fn test_export_world() {
+    let mut world = World::new();
+    let line = FlexLine {
+        locations: vec![Vec2::new(0., 0.), Vec2::new(10., 0.)],
+        connection_style: crate::ConnectionStyle::Unconnected,
+        ..default()
+    };
+    world.spawn((line.clone(), GlobalTransform::from(Transform::from_xyz(5., 5., 1.))));
+    world.spawn(line);
+
+    let svg = world_to_svg(&mut world, SvgExportMode::Path);
+    let first = svg.find(r#"d="M0 0 L10 0""#).unwrap();
+    let second = svg.find(r#"d="M5 5 L15 5""#).unwrap();
+    assert!(first < second);
+}
+
+#[test]
+fn test_export_different_caps() {
+    let line = FlexLine {
+        locations: vec![Vec2::new(0., 0.), Vec2::new(10., 0.), Vec2::new(10., 10.)],
+        width: 2.,
+        start_cap: CapStyle::Round { resolution: 16 },
+        end_cap: CapStyle::Square,
+        connection_style: crate::ConnectionStyle::Unconnected,
+        ..default()
+    };
+    let svg = line.to_svg(SvgExportMode::Path).unwrap();
+    assert!(svg.contains(r#"stroke-linecap="butt""#));
+    // The square cap goes on half the width beyond the end
+    assert!(svg.contains(r#"<path d="M9 10 L9 11 L11 11 L11 10 Z" fill="rgb(255,255,255)"/>"#));
+    // The round cap at the start
+    assert_eq!(svg.matches("<path").count(), 3);
+}
+
+#[test]
+fn test_export_single_point() {
+    // The locations are merged into one, so the caps have no direction
+    let line = FlexLine {
+        locations: vec![Vec2::new(1., 1.), Vec2::new(1., 1.)],
+        start_cap: CapStyle::Round { resolution: 16 },
+        end_cap: CapStyle::Square,
+        connection_style: crate::ConnectionStyle::Unconnected,
+        ..default()
+    };
+    let svg = line.to_svg(SvgExportMode::Path).unwrap();
+    assert!(svg.contains(r#"<path d="M1 1""#));
+}
+
+#[test]
+fn test_export_resolved_colors() {
+    // The profile adds a location in the middle, which gets a blend of the colors instead of wrapping around them
+    let line = FlexLine {
+        locations: vec![Vec2::new(0., 0.), Vec2::new(10., 0.)],
+        color: crate::LineColor::PerVertex(vec![Color::BLACK, Color::WHITE]),
+        width_profile: Some(crate::WidthProfile::keys(vec![(0., 1.), (0.5, 1.), (1., 1.)])),
+        connection_style: crate::ConnectionStyle::Unconnected,
+        ..default()
+    };
+    assert_eq!(line.resolved().locations.len(), 3);
+    let svg = line.to_svg(SvgExportMode::Path).unwrap();
+    assert!(svg.contains(r#"stroke="rgb(127,127,127)""#));
+}
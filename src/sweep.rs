@@ -0,0 +1,219 @@
+use bevy::math::Vec2;
+
+/// A directed edge of a shape.
+/// Shapes going counterclockwise have their bottom edges going right.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Edge {
+    pub from: Vec2,
+    pub to: Vec2,
+    /// Index of the shape the edge belongs to
+    pub shape: usize,
+}
+
+/// An edge going through a slab, with its heights at the left and right side of the slab
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Crossing {
+    pub left: f32,
+    pub right: f32,
+    /// 1 if the edge goes right, so the winding number increases above it, and -1 if it goes left
+    pub winding: i32,
+    pub shape: usize,
+}
+
+impl Crossing {
+    fn mid(&self) -> f32 {
+        (self.left + self.right) / 2.
+    }
+}
+
+/// Cut the plane into vertical slabs at every end point and intersection of the edges.
+/// As no edges cross inside a slab, the edges through it are visited in order from the bottom to the top.
+pub(crate) fn slabs(edges: &[Edge], mut visit: impl FnMut(f32, f32, &[Crossing])) {
+    let mut xs: Vec<f32> = edges.iter().flat_map(|edge| [edge.from.x, edge.to.x]).collect();
+    let sorted = sorted_edges(edges);
+    xs.extend(intersections(&sorted, false));
+    xs.retain(|x| x.is_finite());
+    xs.sort_by(f32::total_cmp);
+    xs.dedup();
+
+    // Edges sorted by their left end, and the ones that might reach the current slab
+    let mut next = 0;
+    let mut active: Vec<(f32, f32, &Edge)> = Vec::new();
+    let mut crossings = Vec::new();
+    for slab in xs.windows(2) {
+        let (x0, x1) = (slab[0], slab[1]);
+        while next < sorted.len() && sorted[next].0 <= x0 {
+            active.push(sorted[next]);
+            next += 1;
+        }
+        active.retain(|(_, max_x, _)| *max_x >= x1);
+
+        crossings.clear();
+        crossings.extend(active.iter().map(|(_, _, edge)| {
+            let height = |x: f32| {
+                let t = (x - edge.from.x) / (edge.to.x - edge.from.x);
+                edge.from.y + (edge.to.y - edge.from.y) * t
+            };
+            Crossing {
+                left: height(x0),
+                right: height(x1),
+                winding: if edge.to.x > edge.from.x { 1 } else { -1 },
+                shape: edge.shape,
+            }
+        }));
+        crossings.sort_by(|a, b| a.mid().total_cmp(&b.mid()));
+        visit(x0, x1, &crossings);
+    }
+}
+
+/// Edges sorted by their left end, with their range in X, so only edges that overlap in X are tested against each other.
+/// Vertical edges are left out, as they do not go through any slab.
+fn sorted_edges(edges: &[Edge]) -> Vec<(f32, f32, &Edge)> {
+    let mut sorted: Vec<(f32, f32, &Edge)> = edges.iter()
+        .filter(|edge| edge.from.x != edge.to.x)
+        .map(|edge| (edge.from.x.min(edge.to.x), edge.from.x.max(edge.to.x), edge))
+        .collect();
+    sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+    sorted
+}
+
+/// X coordinates where the edges cross. With `first`, it stops at the first crossing.
+fn intersections(sorted: &[(f32, f32, &Edge)], first: bool) -> Vec<f32> {
+    let mut xs = Vec::new();
+    for (i, (_, max_x, edge)) in sorted.iter().enumerate() {
+        for (other_min_x, _, other) in &sorted[i + 1..] {
+            if *other_min_x > *max_x {
+                break;
+            }
+            if let Some(x) = segment_intersection_x(edge, other) {
+                xs.push(x);
+                if first {
+                    return xs;
+                }
+            }
+        }
+    }
+    xs
+}
+
+/// Whether any of the edges cross each other away from their ends
+pub(crate) fn any_crossing(edges: &[Edge]) -> bool {
+    !intersections(&sorted_edges(edges), true).is_empty()
+}
+
+/// X coordinate where the segments cross, if they cross away from their ends
+fn segment_intersection_x(a: &Edge, b: &Edge) -> Option<f32> {
+    let (da, db) = (a.to - a.from, b.to - b.from);
+    let denominator = da.perp_dot(db);
+    if denominator.abs() <= f32::EPSILON * da.length() * db.length() {
+        return None;
+    }
+    let offset = b.from - a.from;
+    let t = offset.perp_dot(db) / denominator;
+    let u = offset.perp_dot(da) / denominator;
+    (t > 0. && t < 1. && u > 0. && u < 1.).then_some(a.from.x + da.x * t)
+}
+
+/// The 2 triangles of the trapezoid in a slab between 2 crossings, going counterclockwise
+fn trapezoid(x0: f32, x1: f32, bottom: &Crossing, top: &Crossing) -> [[Vec2; 3]; 2] {
+    let bottom_left = Vec2::new(x0, bottom.left);
+    let bottom_right = Vec2::new(x1, bottom.right);
+    // Crossings that nearly meet can be out of order at one side, from rounding
+    let top_left = Vec2::new(x0, top.left.max(bottom.left));
+    let top_right = Vec2::new(x1, top.right.max(bottom.right));
+    [[bottom_left, bottom_right, top_right], [bottom_left, top_right, top_left]]
+}
+
+/// Triangles covering the inside of the shapes made by the edges.
+/// `inside` tells from the winding number whether a point is inside, as with the fill rules of SVG.
+pub(crate) fn fill(edges: &[Edge], inside: impl Fn(i32) -> bool) -> Vec<[Vec2; 3]> {
+    let mut triangles = Vec::new();
+    slabs(edges, |x0, x1, crossings| {
+        let mut winding = 0;
+        // The crossing where the current piece starts
+        let mut bottom: Option<&Crossing> = None;
+        for crossing in crossings {
+            winding += crossing.winding;
+            match (bottom, inside(winding)) {
+                (None, true) => bottom = Some(crossing),
+                (Some(start), false) => {
+                    triangles.extend(trapezoid(x0, x1, start, crossing));
+                    bottom = None;
+                },
+                _ => {},
+            }
+        }
+    });
+    triangles
+}
+
+/// Split overlapping triangles into pieces that cover each point once.
+/// Each piece comes with the index of the last triangle covering it, which it should take its attributes from.
+pub(crate) fn union(triangles: &[[Vec2; 3]]) -> Vec<(usize, [Vec2; 3])> {
+    let edges: Vec<Edge> = triangles.iter().enumerate().flat_map(|(shape, [a, b, c])| {
+        [(*a, *b), (*b, *c), (*c, *a)].map(|(from, to)| Edge { from, to, shape })
+    }).collect();
+
+    let mut pieces = Vec::new();
+    let mut windings = vec![0; triangles.len()];
+    // Triangles covering the current part of the slab, in the order they were given
+    let mut covering: Vec<usize> = Vec::new();
+    slabs(&edges, |x0, x1, crossings| {
+        covering.clear();
+        // The crossing the current piece starts at, and the triangle it belongs to
+        let mut piece: Option<(&Crossing, usize)> = None;
+        for crossing in crossings {
+            let winding = &mut windings[crossing.shape];
+            let was_inside = *winding != 0;
+            *winding += crossing.winding;
+            match (was_inside, *winding != 0) {
+                (false, true) => {
+                    let index = covering.partition_point(|shape| *shape < crossing.shape);
+                    covering.insert(index, crossing.shape);
+                },
+                (true, false) => covering.retain(|shape| *shape != crossing.shape),
+                _ => {},
+            }
+
+            let top = covering.last().copied();
+            if piece.map(|(_, shape)| shape) != top {
+                if let Some((bottom, shape)) = piece {
+                    pieces.extend(trapezoid(x0, x1, bottom, crossing).map(|triangle| (shape, triangle)));
+                }
+                piece = top.map(|shape| (crossing, shape));
+            }
+        }
+        // Every triangle is left again at the top, but float errors could leave it slightly open
+        for crossing in crossings {
+            windings[crossing.shape] = 0;
+        }
+    });
+    pieces
+}
+
+#[test]
+fn test_union_area() {
+    let area = |pieces: &[(usize, [Vec2; 3])]| pieces.iter()
+        .map(|(_, [a, b, c])| (*b - *a).perp_dot(*c - *a) / 2.)
+        .sum::<f32>();
+
+    // 2 squares overlapping by a quarter, one going clockwise
+    let squares = [
+        [Vec2::new(0., 0.), Vec2::new(2., 0.), Vec2::new(2., 2.)],
+        [Vec2::new(0., 0.), Vec2::new(2., 2.), Vec2::new(0., 2.)],
+        [Vec2::new(1., 1.), Vec2::new(3., 3.), Vec2::new(3., 1.)],
+        [Vec2::new(1., 1.), Vec2::new(1., 3.), Vec2::new(3., 3.)],
+    ];
+    let pieces = union(&squares);
+    assert!((area(&pieces) - 7.).abs() < 1e-4);
+
+    // The overlap takes the attributes of the later square
+    let covering = |point: Vec2| pieces.iter().filter(|(_, [a, b, c])| {
+        let inside = |p: Vec2, q: Vec2| (q - p).perp_dot(point - p) > 0.;
+        inside(*a, *b) && inside(*b, *c) && inside(*c, *a)
+    }).map(|(shape, _)| *shape).collect::<Vec<_>>();
+    assert_eq!(covering(Vec2::new(1.5, 1.4)), vec![2]);
+    assert_eq!(covering(Vec2::new(1.5, 1.6)), vec![3]);
+    assert_eq!(covering(Vec2::new(0.5, 0.4)), vec![0]);
+    assert_eq!(covering(Vec2::new(0.5, 1.6)), vec![1]);
+}
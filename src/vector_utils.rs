@@ -11,10 +11,12 @@ pub enum Orientation {
 pub fn orientation_test(p1: Vec2, p2: Vec2, p3: Vec2) -> Orientation {
     let v1 = p2 - p1;
     let v2 = p3 - p2;
+    // Relative to the lengths, so the result does not depend on the scale
+    let tolerance = 1e-6 * v1.length() * v2.length();
     let det = v1.perp_dot(v2);
-    if det > f32::EPSILON {
+    if det > tolerance {
         Orientation::Left
-    } else if det < -f32::EPSILON {
+    } else if det < -tolerance {
         Orientation::Right
     } else {
         Orientation::Straight
@@ -26,7 +28,7 @@ pub fn orientation_test(p1: Vec2, p2: Vec2, p3: Vec2) -> Orientation {
 pub fn intersection_point(p1: Vec2, d1: Vec2, p2: Vec2, d2: Vec2) -> Option<Vec2> {
     let det = d1.perp_dot(d2);
 
-    if det.abs() < 1e-10 {
+    if det.abs() <= 1e-6 * d1.length() * d2.length() {
         return None; // Lines are parallel or coincident
     }
 
@@ -45,24 +47,25 @@ pub fn intersection_point(p1: Vec2, d1: Vec2, p2: Vec2, d2: Vec2) -> Option<Vec2
     Some(intersection)
 }
 
-pub fn calc_left_side_segment(p1: Vec2, p2: Vec2, width: f32) -> (Vec2, Vec2) {
+/// The left side of the segment from p1 to p2, with the given widths at each end.
+pub fn calc_left_side_segment(p1: Vec2, p2: Vec2, width1: f32, width2: f32) -> (Vec2, Vec2) {
     let vec = p2 - p1;
     let perp = vec.perp().normalize();
-    let start = p1 + perp * width;
-    let end = p2 + perp * width;
+    let start = p1 + perp * width1;
+    let end = p2 + perp * width2;
     (start, end)
 }
 
-pub fn calc_right_side_segment(p1: Vec2, p2: Vec2, width: f32) -> (Vec2, Vec2) {
-    let reverse_side = calc_left_side_segment(p2, p1, width);
+/// The right side of the segment from p1 to p2, with the given widths at each end.
+pub fn calc_right_side_segment(p1: Vec2, p2: Vec2, width1: f32, width2: f32) -> (Vec2, Vec2) {
+    let reverse_side = calc_left_side_segment(p2, p1, width2, width1);
     (reverse_side.1, reverse_side.0)
 }
 pub fn project_point_onto_line(p: Vec2, p1: Vec2, p2: Vec2) -> Vec2 {
     let v = p2 - p1;
     let w = p - p1;
     let proj_factor = w.dot(v) / v.dot(v);
-    let p_proj = p1 + v * proj_factor;
-    p_proj
+    p1 + v * proj_factor
 }
 
 #[test]